@@ -1,19 +1,72 @@
-use std::fs::File;
+use std::{collections::BTreeMap, fs::File};
 use serde::{Deserialize, Serialize};
-use crate::{platform::DEFAULT_MODULES, error::Result};
+use crate::{platform::DEFAULT_MODULES, error::Result, game::offsets::Signature};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Config {
     pub dump_modules: bool,
-    pub modules: Option<Vec<String>>
+    #[serde(default = "enabled")]
+    pub dump_schema: bool,
+    #[serde(default = "enabled")]
+    pub dump_interfaces: bool,
+    #[serde(default = "enabled")]
+    pub dump_vtables: bool,
+    #[serde(default = "enabled")]
+    pub generate_cpp: bool,
+    #[serde(default)]
+    pub cpp_structs: bool,
+    #[serde(default = "enabled")]
+    pub generate_rust: bool,
+    #[serde(default = "enabled")]
+    pub generate_csharp: bool,
+    #[serde(default = "enabled")]
+    pub generate_python: bool,
+    #[serde(default = "enabled")]
+    pub generate_ida: bool,
+    #[serde(default = "enabled")]
+    pub generate_binja: bool,
+    #[serde(default = "enabled")]
+    pub generate_ghidra: bool,
+    // template in templates/ -> file in output/
+    #[serde(default)]
+    pub templates: BTreeMap<String, String>,
+    pub modules: Option<Vec<String>>,
+    #[serde(default)]
+    pub signatures: Option<BTreeMap<String, Signature>>
+}
+
+fn enabled() -> bool {
+    true
 }
 
 impl Config {
     pub fn new() -> Self {
         Self {
             dump_modules: true,
-            modules: Some(DEFAULT_MODULES.iter().map(|&s| s.to_string()).collect())
+            dump_schema: true,
+            dump_interfaces: true,
+            dump_vtables: true,
+            generate_cpp: true,
+            cpp_structs: false,
+            generate_rust: true,
+            generate_csharp: true,
+            generate_python: true,
+            generate_ida: true,
+            generate_binja: true,
+            generate_ghidra: true,
+            templates: BTreeMap::new(),
+            modules: Some(DEFAULT_MODULES.iter().map(|&s| s.to_string()).collect()),
+            signatures: Some(BTreeMap::new())
+        }
+    }
+
+    // the default modules are named for the host, a dump from the other platform needs its own names
+    pub fn with_default_modules(mut self, modules: &[&str]) -> Self {
+        if self.modules.as_ref().is_some_and(|current| current.iter().map(String::as_str).eq(DEFAULT_MODULES)) {
+            self.modules = Some(modules.iter().map(|&s| s.to_string()).collect());
         }
+
+        self
     }
 
     pub fn save(&self) -> Result<()> {
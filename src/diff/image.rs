@@ -0,0 +1,265 @@
+use std::{collections::BTreeMap, fmt::Write as _, fs::{self, create_dir_all, File}, path::Path};
+use serde::{Deserialize, Serialize};
+use crate::{error::Result, image::{self, pe, ImageFormat, Section}};
+use super::{pair, status, Status};
+
+const PAGE_SIZE: usize = 0x1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageDiff {
+    pub old: String,
+    pub new: String,
+    // images rebased to different addresses differ in every relocated pointer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bases: Option<(usize, usize)>,
+    pub sections: Vec<SectionDiff>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionDiff {
+    pub name: String,
+    pub status: Status,
+    pub old: Option<SectionState>,
+    pub new: Option<SectionState>,
+    // section relative byte ranges, a page at a time
+    #[serde(default)]
+    pub changed: Vec<ChangedRange>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionState {
+    pub rva: usize,
+    pub size: usize,
+    pub executable: bool,
+    pub hash: String
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedRange {
+    pub start: usize,
+    pub end: usize
+}
+
+// compares two dumps of one module and writes image_diff.json and image_diff.md to `output`
+pub fn run(old: &Path, new: &Path, output: &Path) {
+    let diff = match ImageDiff::new(old, new) {
+        Ok(diff) => diff,
+        Err(err) => {
+            println!("failed to compare images: {} and {}, error: {}", old.display(), new.display(), err);
+            return;
+        }
+    };
+
+    for section in &diff.sections {
+        println!("{}: {:?}, {} changed pages", section.name, section.status, section.changed_pages());
+    }
+
+    match diff.write(output) {
+        Ok(_) => println!("wrote image diff: {}", output.display()),
+        Err(err) => println!("failed to write image diff: {}, error: {}", output.display(), err)
+    }
+}
+
+impl ImageDiff {
+    pub fn new(old: &Path, new: &Path) -> Result<Self> {
+        let old_data = fs::read(old)?;
+        let new_data = fs::read(new)?;
+
+        let bases = match (ImageFormat::detect(&old_data)?, ImageFormat::detect(&new_data)?) {
+            (ImageFormat::Pe, ImageFormat::Pe) => Some((pe::image_base(&old_data)?, pe::image_base(&new_data)?)),
+            _ => None
+        };
+
+        let old_sections = sections(&old_data)?;
+        let new_sections = sections(&new_data)?;
+
+        let sections = pair(old_sections, new_sections).into_iter()
+            .map(|(name, (old, new))| {
+                let old_bytes = old.as_ref().map(|section| bytes(&old_data, section));
+                let new_bytes = new.as_ref().map(|section| bytes(&new_data, section));
+
+                let changed = match (old_bytes, new_bytes) {
+                    (Some(old), Some(new)) => changed_pages(old, new),
+                    _ => Vec::new()
+                };
+
+                let status = match status(&old, &new) {
+                    Status::Changed if changed.is_empty() && old.as_ref().map(|old| old.size) == new.as_ref().map(|new| new.size) => Status::Unchanged,
+                    status => status
+                };
+
+                SectionDiff {
+                    name,
+                    status,
+                    old: old.map(|section| state(&old_data, &section)),
+                    new: new.map(|section| state(&new_data, &section)),
+                    changed
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            old: old.display().to_string(),
+            new: new.display().to_string(),
+            bases: bases.filter(|(old, new)| old != new),
+            sections
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        create_dir_all(path)?;
+
+        serde_json::to_writer_pretty(
+            File::options()
+                .create(true)
+                .write(true).truncate(true)
+                .open(path.join("image_diff.json"))?,
+            self)?;
+
+        fs::write(path.join("image_diff.md"), self.render()?)?;
+
+        Ok(())
+    }
+
+    fn render(&self) -> std::result::Result<String, std::fmt::Error> {
+        let mut out = String::new();
+
+        writeln!(out, "# Image diff")?;
+        writeln!(out)?;
+        writeln!(out, "`{}` → `{}`", self.old, self.new)?;
+
+        if let Some((old, new)) = self.bases {
+            writeln!(out)?;
+            writeln!(out, "The images were mapped at 0x{:X} and 0x{:X}, relocated pointers differ in every section that holds them.", old, new)?;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "| Section | Status | Old size | New size | Old hash | New hash | Changed pages |")?;
+        writeln!(out, "|---|---|---|---|---|---|---|")?;
+
+        for section in &self.sections {
+            let size = |state: &Option<SectionState>| state.as_ref().map(|state| format!("0x{:X}", state.size)).unwrap_or("-".to_string());
+            let hash = |state: &Option<SectionState>| state.as_ref().map(|state| format!("`{}`", state.hash)).unwrap_or("-".to_string());
+
+            writeln!(
+                out, "| {} | {:?} | {} | {} | {} | {} | {} |",
+                section.name, section.status, size(&section.old), size(&section.new), hash(&section.old), hash(&section.new), section.changed_pages()
+            )?;
+        }
+
+        for section in self.sections.iter().filter(|section| !section.changed.is_empty()) {
+            writeln!(out)?;
+            writeln!(out, "## {}", section.name)?;
+            writeln!(out)?;
+
+            for range in &section.changed {
+                writeln!(out, "- 0x{:X}..0x{:X} (0x{:X} bytes)", range.start, range.end, range.end - range.start)?;
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+impl SectionDiff {
+    pub fn changed_pages(&self) -> usize {
+        self.changed.iter().map(|range| (range.end - range.start).div_ceil(PAGE_SIZE)).sum()
+    }
+}
+
+// sections by name, repeated names are numbered so they still line up
+fn sections(data: &[u8]) -> Result<BTreeMap<String, Section>> {
+    let mut sections: BTreeMap<String, Section> = BTreeMap::new();
+
+    for section in image::sections(data)? {
+        let mut name = section.name.clone();
+        let mut n = 1;
+
+        while sections.contains_key(&name) {
+            n += 1;
+            name = format!("{}#{}", section.name, n);
+        }
+
+        sections.insert(name, section);
+    }
+
+    Ok(sections)
+}
+
+// dumps are memory images, so sections are found at their rva
+fn bytes<'a>(data: &'a [u8], section: &Section) -> &'a [u8] {
+    let start = section.rva.min(data.len());
+    let end = (section.rva + section.size).min(data.len());
+
+    &data[start..end]
+}
+
+fn state(data: &[u8], section: &Section) -> SectionState {
+    SectionState {
+        rva: section.rva,
+        size: section.size,
+        executable: section.executable,
+        hash: format!("{:016x}", fnv1a(bytes(data, section)))
+    }
+}
+
+// pages that differ at the same section offset, a page only one side has counts as changed
+fn changed_pages(old: &[u8], new: &[u8]) -> Vec<ChangedRange> {
+    let mut ranges: Vec<ChangedRange> = Vec::new();
+
+    for start in (0..old.len().max(new.len())).step_by(PAGE_SIZE) {
+        let old_page = old.get(start..(start + PAGE_SIZE).min(old.len()));
+        let new_page = new.get(start..(start + PAGE_SIZE).min(new.len()));
+
+        if old_page == new_page {
+            continue;
+        }
+
+        let end = (start + PAGE_SIZE).min(old.len().max(new.len()));
+
+        match ranges.last_mut() {
+            Some(range) if range.end == start => range.end = end,
+            _ => ranges.push(ChangedRange { start, end })
+        }
+    }
+
+    ranges
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(old: &[u8], new: &[u8]) -> Vec<(usize, usize)> {
+        changed_pages(old, new).into_iter().map(|range| (range.start, range.end)).collect()
+    }
+
+    #[test]
+    fn changed_pages_merge_adjacent_pages() {
+        let old = vec![0u8; 4 * PAGE_SIZE];
+        let mut new = old.clone();
+
+        assert_eq!(ranges(&old, &new), []);
+
+        new[0] = 1;
+        new[PAGE_SIZE + 1] = 1;
+        new[3 * PAGE_SIZE] = 1;
+
+        assert_eq!(ranges(&old, &new), [(0, 2 * PAGE_SIZE), (3 * PAGE_SIZE, 4 * PAGE_SIZE)]);
+    }
+
+    #[test]
+    fn changed_pages_count_pages_only_one_side_has() {
+        let old = vec![0u8; 2 * PAGE_SIZE + 0x10];
+        let new = vec![0u8; PAGE_SIZE];
+
+        assert_eq!(ranges(&old, &new), [(PAGE_SIZE, 2 * PAGE_SIZE + 0x10)]);
+
+        let diff = SectionDiff { name: ".text".to_string(), status: Status::Changed, old: None, new: None, changed: changed_pages(&old, &new) };
+        assert_eq!(diff.changed_pages(), 2);
+    }
+}
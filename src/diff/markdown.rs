@@ -0,0 +1,196 @@
+use std::fmt::{Result, Write};
+use crate::game::offsets::Offset;
+use super::{status, ClassDiff, Diff, EnumDiff, RenameKind, Status};
+
+pub fn render(diff: &Diff) -> std::result::Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(out, "# Diff")?;
+    writeln!(out)?;
+    writeln!(out, "`{}` → `{}`", or_unknown(&diff.old), or_unknown(&diff.new))?;
+    writeln!(out)?;
+    writeln!(out, "| | Added | Removed | Changed |")?;
+    writeln!(out, "|---|---|---|---|")?;
+
+    let counts = |statuses: Vec<Status>| [Status::Added, Status::Removed, Status::Changed]
+        .map(|status| statuses.iter().filter(|other| **other == status).count());
+
+    let rows = [
+        ("Classes", counts(diff.classes.iter().map(|class| class.status).collect())),
+        ("Enums", counts(diff.enums.iter().map(|schema_enum| schema_enum.status).collect())),
+        ("Interfaces", counts(diff.interfaces.iter().map(|interface| status(&interface.old, &interface.new)).collect())),
+        ("Offsets", counts(diff.offsets.iter().map(|offset| status(&offset.old, &offset.new)).collect()))
+    ];
+
+    for (name, [added, removed, changed]) in rows {
+        writeln!(out, "| {} | {} | {} | {} |", name, added, removed, changed)?;
+    }
+
+    write_classes(&mut out, &diff.classes)?;
+    write_enums(&mut out, &diff.enums)?;
+
+    if !diff.interfaces.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Interfaces")?;
+        writeln!(out)?;
+        writeln!(out, "| Module | Interface | Old | New |")?;
+        writeln!(out, "|---|---|---|---|")?;
+
+        for interface in &diff.interfaces {
+            writeln!(out, "| {} | {} | {} | {} |", interface.module, interface.name, or_dash(&interface.old), or_dash(&interface.new))?;
+        }
+    }
+
+    if !diff.offsets.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Offsets")?;
+        writeln!(out)?;
+        writeln!(out, "| Module | Name | Old | New |")?;
+        writeln!(out, "|---|---|---|---|")?;
+
+        for offset in &diff.offsets {
+            let hex = |offset: Option<Offset>| offset.map(|offset| offset.to_string());
+            writeln!(out, "| {} | {} | {} | {} |", offset.module, offset.name, or_dash(&hex(offset.old)), or_dash(&hex(offset.new)))?;
+        }
+    }
+
+    if !diff.renames.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Suggested renames")?;
+        writeln!(out)?;
+        writeln!(out, "| Scope | Old | New | Confidence |")?;
+        writeln!(out, "|---|---|---|---|")?;
+
+        for rename in &diff.renames {
+            let (old, new) = match (rename.kind, &rename.class) {
+                (RenameKind::Field, Some(class)) => (format!("{}::{}", class, rename.old), format!("{}::{}", class, rename.new)),
+                _ => (rename.old.clone(), rename.new.clone())
+            };
+
+            writeln!(out, "| {} | {} | {} | {:.2} |", rename.scope, old, new, rename.confidence)?;
+        }
+    }
+
+    Ok(out)
+}
+
+fn write_classes(out: &mut String, classes: &[ClassDiff]) -> Result {
+    write_names(out, "Added classes", classes.iter().filter(|class| class.status == Status::Added).map(|class| (&class.scope, &class.name)))?;
+    write_names(out, "Removed classes", classes.iter().filter(|class| class.status == Status::Removed).map(|class| (&class.scope, &class.name)))?;
+
+    let changed: Vec<&ClassDiff> = classes.iter().filter(|class| class.status == Status::Changed).collect();
+
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out)?;
+    writeln!(out, "## Changed classes")?;
+
+    for class in changed {
+        writeln!(out)?;
+        writeln!(out, "### {}::{}", class.scope, class.name)?;
+        writeln!(out)?;
+
+        if let Some(size) = &class.size {
+            writeln!(out, "- size 0x{:X} → 0x{:X}", size.old, size.new)?;
+        }
+
+        if let Some(base_classes) = &class.base_classes {
+            writeln!(out, "- base classes `{}` → `{}`", base_classes.old.join(", "), base_classes.new.join(", "))?;
+        }
+
+        for field in &class.fields {
+            match (&field.old, &field.new) {
+                (Some(old), Some(new)) => {
+                    if old.offset != new.offset {
+                        writeln!(out, "- `{}` moved 0x{:X} → 0x{:X}", field.name, old.offset, new.offset)?;
+                    }
+
+                    if old.type_name != new.type_name {
+                        writeln!(out, "- `{}` type `{}` → `{}`", field.name, old.type_name, new.type_name)?;
+                    }
+                },
+                (None, Some(new)) => writeln!(out, "- `{}` added at 0x{:X} (`{}`)", field.name, new.offset, new.type_name)?,
+                (Some(old), None) => writeln!(out, "- `{}` removed from 0x{:X} (`{}`)", field.name, old.offset, old.type_name)?,
+                (None, None) => ()
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_enums(out: &mut String, enums: &[EnumDiff]) -> Result {
+    write_names(out, "Added enums", enums.iter().filter(|schema_enum| schema_enum.status == Status::Added).map(|schema_enum| (&schema_enum.scope, &schema_enum.name)))?;
+    write_names(out, "Removed enums", enums.iter().filter(|schema_enum| schema_enum.status == Status::Removed).map(|schema_enum| (&schema_enum.scope, &schema_enum.name)))?;
+
+    let changed: Vec<&EnumDiff> = enums.iter().filter(|schema_enum| schema_enum.status == Status::Changed).collect();
+
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out)?;
+    writeln!(out, "## Changed enums")?;
+
+    for schema_enum in changed {
+        writeln!(out)?;
+        writeln!(out, "### {}::{}", schema_enum.scope, schema_enum.name)?;
+        writeln!(out)?;
+
+        if let Some(size) = &schema_enum.size {
+            writeln!(out, "- size {} → {}", size.old, size.new)?;
+        }
+
+        if let Some(is_flags) = &schema_enum.is_flags {
+            let kind = |is_flags: bool| match is_flags {
+                true => "flags",
+                false => "plain enum"
+            };
+
+            writeln!(out, "- {} → {}", kind(is_flags.old), kind(is_flags.new))?;
+        }
+
+        for enumerator in &schema_enum.enumerators {
+            match (enumerator.old, enumerator.new) {
+                (Some(old), Some(new)) => writeln!(out, "- `{}` {} → {}", enumerator.name, old, new)?,
+                (None, Some(new)) => writeln!(out, "- `{}` added as {}", enumerator.name, new)?,
+                (Some(old), None) => writeln!(out, "- `{}` removed, was {}", enumerator.name, old)?,
+                (None, None) => ()
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_names<'a>(out: &mut String, title: &str, names: impl Iterator<Item = (&'a String, &'a String)>) -> Result {
+    let names: Vec<(&String, &String)> = names.collect();
+
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out)?;
+    writeln!(out, "## {}", title)?;
+    writeln!(out)?;
+
+    for (scope, name) in names {
+        writeln!(out, "- {}::{}", scope, name)?;
+    }
+
+    Ok(())
+}
+
+fn or_dash(value: &Option<String>) -> &str {
+    value.as_deref().unwrap_or("-")
+}
+
+// directories without a manifest carry no timestamp
+fn or_unknown(timestamp: &str) -> &str {
+    match timestamp.is_empty() {
+        true => "unknown",
+        false => timestamp
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::HashSet;
+use crate::{game::schema::{SchemaClass, SchemaField}, output::model::Model};
+use super::{classes, ClassDiff, Rename, RenameKind, Status};
+
+// pairs scoring below this are not worth suggesting
+const MIN_CONFIDENCE: f32 = 0.6;
+
+// a class that moved to another scope is less likely to be the same one
+const SCOPE_PENALTY: f32 = 0.9;
+
+pub fn renames(old: &Model, new: &Model, diff: &[ClassDiff]) -> Vec<Rename> {
+    let old_classes = classes(old);
+    let new_classes = classes(new);
+
+    let removed: Vec<(&String, &SchemaClass)> = diff.iter()
+        .filter(|class| class.status == Status::Removed)
+        .filter_map(|class| old_classes.get(&(class.scope.clone(), class.name.clone())).map(|old| (&class.scope, *old)))
+        .collect();
+
+    let added: Vec<(&String, &SchemaClass)> = diff.iter()
+        .filter(|class| class.status == Status::Added)
+        .filter_map(|class| new_classes.get(&(class.scope.clone(), class.name.clone())).map(|new| (&class.scope, *new)))
+        .collect();
+
+    let mut candidates: Vec<(f32, usize, usize)> = Vec::new();
+
+    for (i, (old_scope, old)) in removed.iter().enumerate() {
+        for (j, (new_scope, new)) in added.iter().enumerate() {
+            let mut confidence = class_similarity(old, new);
+
+            if old_scope != new_scope {
+                confidence *= SCOPE_PENALTY;
+            }
+
+            if confidence >= MIN_CONFIDENCE {
+                candidates.push((confidence, i, j));
+            }
+        }
+    }
+
+    let mut renames: Vec<Rename> = Vec::new();
+
+    // classes whose fields are compared for renames, kept or renamed
+    let mut pairs: Vec<(&String, &SchemaClass, &SchemaClass)> = diff.iter()
+        .filter(|class| class.status == Status::Changed)
+        .filter_map(|class| {
+            let key = (class.scope.clone(), class.name.clone());
+            Some((&class.scope, *old_classes.get(&key)?, *new_classes.get(&key)?))
+        })
+        .collect();
+
+    for (confidence, i, j) in assign(candidates) {
+        let ((_, old), (scope, new)) = (removed[i], added[j]);
+
+        renames.push(Rename {
+            kind: RenameKind::Class,
+            scope: scope.clone(),
+            class: None,
+            old: old.name.clone(),
+            new: new.name.clone(),
+            confidence: round(confidence)
+        });
+
+        pairs.push((scope, old, new));
+    }
+
+    for (scope, old, new) in pairs {
+        renames.extend(field_renames(scope, old, new));
+    }
+
+    renames
+}
+
+fn field_renames(scope: &str, old: &SchemaClass, new: &SchemaClass) -> Vec<Rename> {
+    let old_names: HashSet<&str> = old.fields.iter().map(|field| field.name.as_str()).collect();
+    let new_names: HashSet<&str> = new.fields.iter().map(|field| field.name.as_str()).collect();
+
+    let removed: Vec<&SchemaField> = old.fields.iter().filter(|field| !new_names.contains(field.name.as_str())).collect();
+    let added: Vec<&SchemaField> = new.fields.iter().filter(|field| !old_names.contains(field.name.as_str())).collect();
+
+    let mut candidates: Vec<(f32, usize, usize)> = Vec::new();
+
+    for (i, old_field) in removed.iter().enumerate() {
+        for (j, new_field) in added.iter().enumerate() {
+            let confidence = field_similarity(old, old_field, new, new_field);
+
+            if confidence >= MIN_CONFIDENCE {
+                candidates.push((confidence, i, j));
+            }
+        }
+    }
+
+    let mut renames: Vec<Rename> = assign(candidates).into_iter()
+        .map(|(confidence, i, j)| Rename {
+            kind: RenameKind::Field,
+            scope: scope.to_string(),
+            class: Some(new.name.clone()),
+            old: removed[i].name.clone(),
+            new: added[j].name.clone(),
+            confidence: round(confidence)
+        })
+        .collect();
+
+    renames.sort_by(|a, b| a.old.cmp(&b.old));
+
+    renames
+}
+
+// field types in layout order, sizes and metadata weighed by how much they say about identity
+fn class_similarity(old: &SchemaClass, new: &SchemaClass) -> f32 {
+    // without fields there's nothing that tells two classes apart
+    if old.fields.is_empty() || new.fields.is_empty() {
+        return 0.0;
+    }
+
+    let old_types = field_types(old);
+    let new_types = field_types(new);
+
+    let types = common_subsequence(&old_types, &new_types) as f32 / old_types.len().max(new_types.len()) as f32;
+
+    let size = match old.size.max(new.size) {
+        0 => 1.0,
+        max => old.size.min(new.size).max(0) as f32 / max as f32
+    };
+
+    let metadata = jaccard(
+        old.metadata.iter().map(|metadata| metadata.name.as_str()).collect(),
+        new.metadata.iter().map(|metadata| metadata.name.as_str()).collect()
+    );
+
+    let base_classes = match old.base_classes == new.base_classes {
+        true => 1.0,
+        false => 0.0
+    };
+
+    0.55 * types + 0.2 * size + 0.1 * metadata + 0.15 * base_classes
+}
+
+// same type, same place relative to the class size and the same neighbours
+fn field_similarity(old_class: &SchemaClass, old: &SchemaField, new_class: &SchemaClass, new: &SchemaField) -> f32 {
+    let type_name = match old.type_name == new.type_name {
+        true => 1.0,
+        false => 0.0
+    };
+
+    let position = match (old_class.size, new_class.size) {
+        (old_size, new_size) if old_size > 0 && new_size > 0 => {
+            1.0 - (old.offset as f32 / old_size as f32 - new.offset as f32 / new_size as f32).abs().min(1.0)
+        },
+        _ => match old.offset == new.offset {
+            true => 1.0,
+            false => 0.0
+        }
+    };
+
+    let neighbours = [-1, 1].iter()
+        .filter(|&&direction| {
+            let old_neighbour = neighbour(old_class, old, direction);
+            old_neighbour.is_some() && old_neighbour == neighbour(new_class, new, direction)
+        })
+        .count() as f32 / 2.0;
+
+    0.5 * type_name + 0.3 * position + 0.2 * neighbours
+}
+
+// the name of the field before or after another one in layout order
+fn neighbour<'a>(class: &'a SchemaClass, field: &SchemaField, direction: i32) -> Option<&'a str> {
+    let mut fields: Vec<&SchemaField> = class.fields.iter().collect();
+    fields.sort_by_key(|field| field.offset);
+
+    let index = fields.iter().position(|other| other.name == field.name)?;
+    let index = index.checked_add_signed(direction as isize)?;
+
+    fields.get(index).map(|field| field.name.as_str())
+}
+
+fn field_types(class: &SchemaClass) -> Vec<&str> {
+    let mut fields: Vec<&SchemaField> = class.fields.iter().collect();
+    fields.sort_by_key(|field| field.offset);
+
+    fields.into_iter().map(|field| field.type_name.as_str()).collect()
+}
+
+fn common_subsequence(a: &[&str], b: &[&str]) -> usize {
+    let mut lengths = vec![0usize; b.len() + 1];
+
+    for a in a {
+        let mut diagonal = 0;
+
+        for (j, b) in b.iter().enumerate() {
+            let above = lengths[j + 1];
+
+            lengths[j + 1] = match a == b {
+                true => diagonal + 1,
+                false => above.max(lengths[j])
+            };
+
+            diagonal = above;
+        }
+    }
+
+    lengths[b.len()]
+}
+
+fn jaccard(a: HashSet<&str>, b: HashSet<&str>) -> f32 {
+    match a.union(&b).count() {
+        0 => 1.0,
+        union => a.intersection(&b).count() as f32 / union as f32
+    }
+}
+
+// greedily takes the most confident pairs, using every candidate on either side at most once
+fn assign(mut candidates: Vec<(f32, usize, usize)>) -> Vec<(f32, usize, usize)> {
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+    let mut used_old: HashSet<usize> = HashSet::new();
+    let mut used_new: HashSet<usize> = HashSet::new();
+
+    candidates.into_iter()
+        .filter(|(_, i, j)| {
+            if used_old.contains(i) || used_new.contains(j) {
+                return false;
+            }
+
+            used_old.insert(*i);
+            used_new.insert(*j);
+
+            true
+        })
+        .collect()
+}
+
+fn round(confidence: f32) -> f32 {
+    (confidence * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{offsets::Offsets, schema::SchemaScope};
+    use super::super::Diff;
+    use super::*;
+
+    fn class(name: &str, size: i32, fields: &[(&str, i32, &str)]) -> SchemaClass {
+        SchemaClass {
+            name: name.to_string(),
+            module: "client.dll".to_string(),
+            size,
+            base_classes: Vec::new(),
+            fields: fields.iter()
+                .map(|(name, offset, type_name)| SchemaField { name: name.to_string(), offset: *offset, type_name: type_name.to_string(), metadata: Vec::new() })
+                .collect(),
+            metadata: Vec::new()
+        }
+    }
+
+    fn model(classes: Vec<SchemaClass>) -> Model {
+        let scope = SchemaScope { name: "client.dll".to_string(), classes, enums: Vec::new() };
+        Model::new(Vec::new(), vec![scope], Vec::new(), Vec::new(), Offsets::new())
+    }
+
+    #[test]
+    fn common_subsequence_keeps_order() {
+        assert_eq!(common_subsequence(&["a", "b", "c", "d"], &["a", "c", "b", "d"]), 3);
+        assert_eq!(common_subsequence(&["a", "b"], &["c"]), 0);
+        assert_eq!(common_subsequence(&[], &["a"]), 0);
+    }
+
+    #[test]
+    fn jaccard_of_sets() {
+        assert_eq!(jaccard(HashSet::from(["a", "b"]), HashSet::from(["b", "c"])), 1.0 / 3.0);
+        assert_eq!(jaccard(HashSet::from(["a"]), HashSet::from(["a"])), 1.0);
+        assert_eq!(jaccard(HashSet::new(), HashSet::new()), 1.0);
+    }
+
+    #[test]
+    fn assign_uses_each_side_once() {
+        let assigned = assign(vec![(0.7, 0, 0), (0.9, 0, 1), (0.8, 1, 1), (0.65, 1, 0)]);
+
+        assert_eq!(assigned, [(0.9, 0, 1), (0.65, 1, 0)]);
+    }
+
+    #[test]
+    fn class_similarity_needs_fields() {
+        let fields = [("m_iHealth", 0x10, "int32"), ("m_vecOrigin", 0x14, "Vector")];
+
+        assert_eq!(class_similarity(&class("A", 0x20, &fields), &class("B", 0x20, &fields)), 1.0);
+        assert_eq!(class_similarity(&class("A", 0x20, &[]), &class("B", 0x20, &[])), 0.0);
+    }
+
+    #[test]
+    fn renamed_classes_and_fields_are_suggested() {
+        let old = model(vec![
+            class("C_OldName", 0x20, &[("m_iHealth", 0x10, "int32"), ("m_vecOrigin", 0x14, "Vector")]),
+            class("C_Kept", 0x10, &[("m_flOld", 0x8, "float32"), ("m_bAlive", 0xC, "bool")])
+        ]);
+
+        let new = model(vec![
+            class("C_NewName", 0x20, &[("m_iHealth", 0x10, "int32"), ("m_vecOrigin", 0x14, "Vector")]),
+            class("C_Kept", 0x10, &[("m_flNew", 0x8, "float32"), ("m_bAlive", 0xC, "bool")]),
+            class("C_Unrelated", 0x100, &[("m_szName", 0x0, "char[64]")])
+        ]);
+
+        let diff = Diff::new(&old, &new);
+
+        let renames: Vec<(RenameKind, Option<&str>, &str, &str)> = diff.renames.iter()
+            .map(|rename| (rename.kind, rename.class.as_deref(), rename.old.as_str(), rename.new.as_str()))
+            .collect();
+
+        assert_eq!(renames, [
+            (RenameKind::Class, None, "C_OldName", "C_NewName"),
+            (RenameKind::Field, Some("C_Kept"), "m_flOld", "m_flNew")
+        ]);
+    }
+}
@@ -0,0 +1,373 @@
+pub mod image;
+mod markdown;
+mod matching;
+
+use std::{collections::BTreeMap, fs::{create_dir_all, File}, path::Path};
+use serde::{Deserialize, Serialize};
+use crate::{error::Result, game::{offsets::Offset, schema::{SchemaClass, SchemaEnum}}, output::model::Model};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Added,
+    Removed,
+    Changed,
+    Unchanged
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T
+}
+
+// what changed between two runs, anything equal in both is left out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diff {
+    pub old: String,
+    pub new: String,
+    pub classes: Vec<ClassDiff>,
+    pub enums: Vec<EnumDiff>,
+    pub interfaces: Vec<InterfaceDiff>,
+    pub offsets: Vec<OffsetDiff>,
+    // removed and added classes and fields that look like the same thing under a new name
+    #[serde(default)]
+    pub renames: Vec<Rename>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassDiff {
+    pub scope: String,
+    pub name: String,
+    pub status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<Change<i32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_classes: Option<Change<Vec<String>>>,
+    // only filled in for changed classes
+    #[serde(default)]
+    pub fields: Vec<FieldDiff>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Field {
+    pub offset: i32,
+    pub type_name: String
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldDiff {
+    pub name: String,
+    pub old: Option<Field>,
+    pub new: Option<Field>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnumDiff {
+    pub scope: String,
+    pub name: String,
+    pub status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<Change<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_flags: Option<Change<bool>>,
+    #[serde(default)]
+    pub enumerators: Vec<EnumeratorDiff>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnumeratorDiff {
+    pub name: String,
+    pub old: Option<i64>,
+    pub new: Option<i64>
+}
+
+// interfaces are matched by their name without the version suffix, Source2Client002 -> Source2Client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterfaceDiff {
+    pub module: String,
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenameKind {
+    Class,
+    Field
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rename {
+    pub kind: RenameKind,
+    pub scope: String,
+    // the class a field belongs to, under its new name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    pub old: String,
+    pub new: String,
+    // 0 to 1, how alike the two are structurally
+    pub confidence: f32
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OffsetDiff {
+    pub module: String,
+    pub name: String,
+    pub old: Option<Offset>,
+    pub new: Option<Offset>
+}
+
+// loads two manifests or output directories and writes diff.json and diff.md to `output`
+pub fn run(old: &Path, new: &Path, output: &Path) {
+    let mut models = Vec::new();
+
+    for path in [old, new] {
+        match Model::load(path) {
+            Ok(model) => models.push(model),
+            Err(err) => {
+                println!("failed to load dump: {}, error: {}", path.display(), err);
+                return;
+            }
+        }
+    }
+
+    let diff = Diff::new(&models[0], &models[1]);
+
+    println!(
+        "diff: {} classes, {} enums, {} interfaces and {} offsets changed, {} suggested renames",
+        diff.classes.len(), diff.enums.len(), diff.interfaces.len(), diff.offsets.len(), diff.renames.len()
+    );
+
+    match diff.write(output) {
+        Ok(_) => println!("wrote diff: {}", output.display()),
+        Err(err) => println!("failed to write diff: {}, error: {}", output.display(), err)
+    }
+}
+
+impl Diff {
+    pub fn new(old: &Model, new: &Model) -> Self {
+        let classes = diff_classes(old, new);
+        let renames = matching::renames(old, new, &classes);
+
+        Self {
+            old: old.timestamp.clone(),
+            new: new.timestamp.clone(),
+            classes,
+            enums: diff_enums(old, new),
+            interfaces: diff_interfaces(old, new),
+            offsets: diff_offsets(old, new),
+            renames
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        create_dir_all(path)?;
+
+        serde_json::to_writer_pretty(
+            File::options()
+                .create(true)
+                .write(true).truncate(true)
+                .open(path.join("diff.json"))?,
+            self)?;
+
+        std::fs::write(path.join("diff.md"), markdown::render(self)?)?;
+
+        Ok(())
+    }
+}
+
+fn diff_classes(old: &Model, new: &Model) -> Vec<ClassDiff> {
+    pair(classes(old), classes(new)).into_iter()
+        .filter_map(|((scope, name), (old, new))| {
+            let mut diff = ClassDiff {
+                scope,
+                name,
+                status: status(&old, &new),
+                size: None,
+                base_classes: None,
+                fields: Vec::new()
+            };
+
+            if let (Some(old), Some(new)) = (old, new) {
+                diff.size = changed(old.size, new.size);
+                diff.base_classes = changed(old.base_classes.clone(), new.base_classes.clone());
+                diff.fields = diff_fields(old, new);
+
+                if diff.size.is_none() && diff.base_classes.is_none() && diff.fields.is_empty() {
+                    return None;
+                }
+            }
+
+            Some(diff)
+        })
+        .collect()
+}
+
+fn diff_fields(old: &SchemaClass, new: &SchemaClass) -> Vec<FieldDiff> {
+    let fields = |class: &SchemaClass| -> BTreeMap<String, Field> {
+        class.fields.iter().map(|field| (field.name.clone(), Field { offset: field.offset, type_name: field.type_name.clone() })).collect()
+    };
+
+    let mut fields: Vec<FieldDiff> = pair(fields(old), fields(new)).into_iter()
+        .filter(|(_, (old, new))| old != new)
+        .map(|(name, (old, new))| FieldDiff { name, old, new })
+        .collect();
+
+    // in layout order rather than by name
+    fields.sort_by_key(|field| field.new.as_ref().or(field.old.as_ref()).map(|field| field.offset));
+
+    fields
+}
+
+fn diff_enums(old: &Model, new: &Model) -> Vec<EnumDiff> {
+    fn enums(model: &Model) -> BTreeMap<(String, String), &SchemaEnum> {
+        model.schema.iter()
+            .flat_map(|scope| scope.enums.iter().map(move |schema_enum| ((scope.name.clone(), schema_enum.name.clone()), schema_enum)))
+            .collect()
+    }
+
+    let enumerators = |schema_enum: &SchemaEnum| -> BTreeMap<String, i64> {
+        schema_enum.enumerators.iter().map(|enumerator| (enumerator.name.clone(), enumerator.value)).collect()
+    };
+
+    pair(enums(old), enums(new)).into_iter()
+        .filter_map(|((scope, name), (old, new))| {
+            let mut diff = EnumDiff {
+                scope,
+                name,
+                status: status(&old, &new),
+                size: None,
+                is_flags: None,
+                enumerators: Vec::new()
+            };
+
+            if let (Some(old), Some(new)) = (old, new) {
+                diff.size = changed(old.size, new.size);
+                diff.is_flags = changed(old.is_flags, new.is_flags);
+                diff.enumerators = pair(enumerators(old), enumerators(new)).into_iter()
+                    .filter(|(_, (old, new))| old != new)
+                    .map(|(name, (old, new))| EnumeratorDiff { name, old, new })
+                    .collect();
+
+                if diff.size.is_none() && diff.is_flags.is_none() && diff.enumerators.is_empty() {
+                    return None;
+                }
+            }
+
+            Some(diff)
+        })
+        .collect()
+}
+
+fn diff_interfaces(old: &Model, new: &Model) -> Vec<InterfaceDiff> {
+    // a module can export several versions of an interface, the latest one is compared
+    let interfaces = |model: &Model| -> BTreeMap<(String, String), String> {
+        let mut latest: BTreeMap<(String, String), String> = BTreeMap::new();
+
+        for interfaces in &model.interfaces {
+            for interface in &interfaces.interfaces {
+                let name = interface.name.trim_end_matches(|c: char| c.is_ascii_digit()).to_string();
+
+                latest.entry((interfaces.module.clone(), name))
+                    .and_modify(|latest| *latest = latest.clone().max(interface.name.clone()))
+                    .or_insert_with(|| interface.name.clone());
+            }
+        }
+
+        latest
+    };
+
+    pair(interfaces(old), interfaces(new)).into_iter()
+        .filter(|(_, (old, new))| old != new)
+        .map(|((module, name), (old, new))| InterfaceDiff { module, name, old, new })
+        .collect()
+}
+
+fn diff_offsets(old: &Model, new: &Model) -> Vec<OffsetDiff> {
+    let offsets = |model: &Model| -> BTreeMap<(String, String), Offset> {
+        model.offsets.iter()
+            .flat_map(|(module, offsets)| offsets.iter().map(|(name, offset)| ((module.clone(), name.clone()), *offset)))
+            .collect()
+    };
+
+    pair(offsets(old), offsets(new)).into_iter()
+        .filter(|(_, (old, new))| old != new)
+        .map(|((module, name), (old, new))| OffsetDiff { module, name, old, new })
+        .collect()
+}
+
+// classes by scope and name
+fn classes(model: &Model) -> BTreeMap<(String, String), &SchemaClass> {
+    model.schema.iter()
+        .flat_map(|scope| scope.classes.iter().map(move |class| ((scope.name.clone(), class.name.clone()), class)))
+        .collect()
+}
+
+// joins two maps on their keys
+fn pair<K: Ord, V>(old: BTreeMap<K, V>, mut new: BTreeMap<K, V>) -> BTreeMap<K, (Option<V>, Option<V>)> {
+    let mut paired: BTreeMap<K, (Option<V>, Option<V>)> = BTreeMap::new();
+
+    for (key, old) in old {
+        let new = new.remove(&key);
+        paired.insert(key, (Some(old), new));
+    }
+
+    for (key, new) in new {
+        paired.insert(key, (None, Some(new)));
+    }
+
+    paired
+}
+
+fn status<T>(old: &Option<T>, new: &Option<T>) -> Status {
+    match (old, new) {
+        (Some(_), Some(_)) => Status::Changed,
+        (None, _) => Status::Added,
+        (_, None) => Status::Removed
+    }
+}
+
+fn changed<T: PartialEq>(old: T, new: T) -> Option<Change<T>> {
+    match old == new {
+        true => None,
+        false => Some(Change { old, new })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{offsets::Offsets, schema::{SchemaEnumerator, SchemaScope}};
+    use super::*;
+
+    fn model(size: u8, is_flags: bool) -> Model {
+        let schema_enum = SchemaEnum {
+            name: "Team".to_string(),
+            module: "client.dll".to_string(),
+            size,
+            is_flags,
+            enumerators: vec![SchemaEnumerator { name: "TEAM_CT".to_string(), value: 3 }]
+        };
+
+        let scope = SchemaScope { name: "client.dll".to_string(), classes: Vec::new(), enums: vec![schema_enum] };
+
+        Model::new(Vec::new(), vec![scope], Vec::new(), Vec::new(), Offsets::new())
+    }
+
+    #[test]
+    fn enums_with_the_same_enumerators_are_unchanged() {
+        assert!(diff_enums(&model(1, false), &model(1, false)).is_empty());
+    }
+
+    #[test]
+    fn enums_changing_size_or_flags_are_changed() {
+        let diff = diff_enums(&model(1, false), &model(4, true));
+
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].status, Status::Changed);
+        assert_eq!(diff[0].size, Some(Change { old: 1, new: 4 }));
+        assert_eq!(diff[0].is_flags, Some(Change { old: false, new: true }));
+        assert!(diff[0].enumerators.is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-#![allow(dead_code)]
+#![allow(dead_code, clippy::enum_variant_names)]
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -10,6 +10,10 @@ pub enum Error {
     AlreadyAttached,
     #[error("invalid image")]
     InvalidImage,
+    #[error("invalid pattern: {0}")]
+    InvalidPattern(String),
+    #[error("invalid operation: {0}")]
+    InvalidOperation(String),
 
     #[error(transparent)]
     Utf8Error(#[from] std::str::Utf8Error),
@@ -17,8 +21,9 @@ pub enum Error {
     IoError(#[from] std::io::Error),
     #[error(transparent)]
     JsonError(#[from] serde_json::Error),
+    #[error(transparent)]
+    FmtError(#[from] std::fmt::Error),
 
-    #[cfg(target_os = "windows")]
     #[error(transparent)]
     PeliteError(#[from] pelite::Error),
 
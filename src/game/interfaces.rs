@@ -0,0 +1,285 @@
+use crate::{config::Config, error::{Error, Result}, image::FromBytes, platform::{ProcessModule, ProcessTrait}};
+use std::{fs::{create_dir_all, File}, path::Path};
+use iced_x86::{Decoder, DecoderOptions, Mnemonic, OpKind, Register};
+use serde::{Deserialize, Serialize};
+
+const MAX_INTERFACES: usize = 0x400;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleInterfaces {
+    pub module: String,
+    pub interfaces: Vec<Interface>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interface {
+    pub name: String,
+    pub factory_rva: usize,
+    pub instance: Option<usize>,
+    // first pointer of the instance, every interface is polymorphic
+    #[serde(default)]
+    pub vtable: Option<usize>
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct InterfaceReg {
+    create_fn: u64,                 // 0x0000
+    name: u64,                      // 0x0008
+    next: u64                       // 0x0010
+}
+
+unsafe impl FromBytes for InterfaceReg {}
+
+pub fn dump(process: &impl ProcessTrait, config: &Config) -> Vec<ModuleInterfaces> {
+    let modules = match &config.modules {
+        Some(modules) => modules,
+        None => return Vec::new()
+    };
+
+    let mut dumped = Vec::new();
+
+    for mod_name in modules {
+        let module = match process.mod_find(mod_name) {
+            Ok(module) => module,
+            Err(err) => {
+                println!("failed to find module: {}, error: {}", mod_name, err);
+                continue
+            }
+        };
+
+        let interfaces = match read_interfaces(process, &module) {
+            Ok(interfaces) => interfaces,
+            Err(err) => {
+                println!("failed to read interfaces: {}, error: {}", mod_name, err);
+                continue
+            }
+        };
+
+        let interfaces = ModuleInterfaces {
+            module: mod_name.clone(),
+            interfaces
+        };
+
+        match write_interfaces(&interfaces) {
+            Ok(_) => println!("dumped interfaces: {} ({} interfaces)", mod_name, interfaces.interfaces.len()),
+            Err(err) => println!("failed to write interfaces: {}, error: {}", mod_name, err)
+        }
+
+        dumped.push(interfaces);
+    }
+
+    dumped
+}
+
+pub fn find_interface(process: &impl ProcessTrait, module: &ProcessModule, name: &str) -> Result<usize> {
+    match read_interfaces(process, module)?.into_iter().find(|interface| interface.name == name) {
+        Some(Interface { instance: Some(instance), .. }) => Ok(instance),
+        _ => Err(Error::NotFound)
+    }
+}
+
+fn write_interfaces(interfaces: &ModuleInterfaces) -> Result<()> {
+    let file_path = Path::new("output/interfaces").join(&interfaces.module).with_extension("json");
+
+    create_dir_all(file_path.parent().unwrap())?;
+
+    Ok(serde_json::to_writer_pretty(
+        File::options()
+            .create(true)
+            .write(true).truncate(true)
+            .open(&file_path)?,
+        interfaces)?)
+}
+
+fn read_interfaces(process: &impl ProcessTrait, module: &ProcessModule) -> Result<Vec<Interface>> {
+    let create_interface = process.mod_export(module, "CreateInterface")?;
+
+    // CreateInterface loads s_pInterfaceRegs with a `mov reg, [rip + s_pInterfaceRegs]` before walking it
+    let code: [u8; 0x40] = process.read(create_interface)?;
+
+    let interface_regs = match (0..code.len() - 7).find(|&i|
+        (code[i] == 0x48 || code[i] == 0x4C) && code[i + 1] == 0x8B && code[i + 2] & 0xC7 == 0x05) {
+        Some(i) => rip_relative(&code, create_interface, i),
+        None => return Err(Error::NotFound)
+    };
+
+    let mut interfaces: Vec<Interface> = Vec::new();
+
+    let mut interface_reg = process.read::<u64>(interface_regs)? as usize;
+
+    while interface_reg != 0 && interfaces.len() < MAX_INTERFACES {
+        let reg: InterfaceReg = process.read(interface_reg)?;
+
+        let name = process.read_string(reg.name as usize)?;
+
+        // the interface is still worth listing when its factory can't be followed
+        let instance = match resolve_factory(process, reg.create_fn as usize) {
+            Ok(instance) => instance,
+            Err(err) => {
+                println!("failed to resolve interface factory: {}, error: {}", name, err);
+                None
+            }
+        };
+
+        interfaces.push(Interface {
+            name,
+            factory_rva: (reg.create_fn as usize).wrapping_sub(module.module_base),
+            instance,
+            vtable: instance.and_then(|instance| process.read::<u64>(instance).ok()).map(|vtable| vtable as usize)
+        });
+
+        interface_reg = reg.next as usize;
+    }
+
+    interfaces.reverse();
+
+    Ok(interfaces)
+}
+
+// most factories boil down to `lea rax, [rip + instance]; ret` or `mov rax, [rip + instance]; ret`,
+// anything that constructs its instance on demand can't be resolved statically
+fn resolve_factory(process: &impl ProcessTrait, create_fn: usize) -> Result<Option<usize>> {
+    let code: [u8; 0x20] = process.read(create_fn)?;
+
+    let decoder = Decoder::with_ip(64, &code, create_fn as u64, DecoderOptions::NONE);
+
+    for instruction in decoder {
+        if instruction.is_invalid() || instruction.mnemonic() == Mnemonic::Ret {
+            break;
+        }
+
+        if instruction.op0_register() != Register::RAX || instruction.op1_kind() != OpKind::Memory || !instruction.is_ip_rel_memory_operand() {
+            continue;
+        }
+
+        let target = instruction.ip_rel_memory_address() as usize;
+
+        match instruction.mnemonic() {
+            Mnemonic::Lea => return Ok(Some(target)),
+            Mnemonic::Mov => return Ok(Some(process.read::<u64>(target)? as usize)),
+            _ => ()
+        }
+    }
+
+    Ok(None)
+}
+
+fn rip_relative(code: &[u8], address: usize, offset: usize) -> usize {
+    let displacement = i32::from_le_bytes(code[offset + 3..offset + 7].try_into().unwrap());
+    (address + offset + 7).wrapping_add_signed(displacement as isize)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{image::write, platform::tests::Memory};
+    use super::*;
+
+    const BASE: usize = 0x10000;
+
+    const CREATE_INTERFACE: usize = 0x100;
+    const INTERFACE_REGS: usize = 0x800;
+
+    // somewhere the test memory doesn't cover
+    const UNMAPPED: u64 = 0xDEAD0000;
+
+    // `opcode reg, [rip + target]` placed at `offset`
+    fn rip_instruction(data: &mut [u8], offset: usize, opcode: [u8; 3], target: usize) -> usize {
+        let displacement = target as i32 - (offset + 7) as i32;
+        data[offset..offset + 3].copy_from_slice(&opcode);
+        data[offset + 3..offset + 7].copy_from_slice(&displacement.to_le_bytes());
+        offset + 7
+    }
+
+    fn string(data: &mut [u8], offset: usize, value: &str) -> u64 {
+        data[offset..offset + value.len()].copy_from_slice(value.as_bytes());
+        (BASE + offset) as u64
+    }
+
+    // a module whose CreateInterface loads s_pInterfaceRegs, which points at `first`
+    fn module(first: usize) -> Vec<u8> {
+        let mut data = vec![0u8; 0x1000];
+
+        let end = rip_instruction(&mut data, CREATE_INTERFACE, [0x48, 0x8B, 0x05], INTERFACE_REGS);
+        data[end] = 0xC3;
+
+        write(&mut data, INTERFACE_REGS, (BASE + first) as u64).unwrap();
+
+        data
+    }
+
+    #[test]
+    fn read_interfaces_walks_the_registration_list() {
+        let mut data = module(0x300);
+
+        // lea rax, [rip + instance]; ret
+        let end = rip_instruction(&mut data, 0x400, [0x48, 0x8D, 0x05], 0x900);
+        data[end] = 0xC3;
+        write(&mut data, 0x900, 0x1234u64).unwrap();
+
+        // mov eax, 0xC3; mov rax, [rip + pointer]; ret, the immediate isn't a ret
+        data[0x440..0x445].copy_from_slice(&[0xB8, 0xC3, 0x00, 0x00, 0x00]);
+        let end = rip_instruction(&mut data, 0x445, [0x48, 0x8B, 0x05], 0x910);
+        data[end] = 0xC3;
+        write(&mut data, 0x910, (BASE + 0x920) as u64).unwrap();
+        write(&mut data, 0x920, 0x5678u64).unwrap();
+
+        let regs = [
+            (0x300, (BASE + 0x400) as u64, "First_001", 0x330),
+            (0x330, (BASE + 0x440) as u64, "Second_001", 0x360),
+            (0x360, UNMAPPED, "Broken_001", 0)
+        ];
+
+        for (offset, create_fn, name, next) in regs {
+            let name = string(&mut data, 0xA00 + offset, name);
+            let next = match next {
+                0 => 0,
+                next => (BASE + next) as u64
+            };
+            write(&mut data, offset, InterfaceReg { create_fn, name, next }).unwrap();
+        }
+
+        let mut process = Memory::new(BASE, data);
+        process.exports.push(("CreateInterface", BASE + CREATE_INTERFACE));
+
+        let interfaces = read_interfaces(&process, &process.module()).unwrap();
+
+        let interfaces: Vec<(&str, usize, Option<usize>, Option<usize>)> = interfaces.iter()
+            .map(|interface| (interface.name.as_str(), interface.factory_rva, interface.instance, interface.vtable))
+            .collect();
+
+        assert_eq!(interfaces, [
+            ("Broken_001", (UNMAPPED as usize).wrapping_sub(BASE), None, None),
+            ("Second_001", 0x440, Some(BASE + 0x920), Some(0x5678)),
+            ("First_001", 0x400, Some(BASE + 0x900), Some(0x1234))
+        ]);
+
+        assert_eq!(find_interface(&process, &process.module(), "Second_001").unwrap(), BASE + 0x920);
+        assert!(matches!(find_interface(&process, &process.module(), "Broken_001"), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn read_interfaces_stops_on_a_cycle() {
+        let mut data = module(0x300);
+
+        let name = string(&mut data, 0xA00, "Loop_001");
+        write(&mut data, 0x300, InterfaceReg { create_fn: UNMAPPED, name, next: (BASE + 0x300) as u64 }).unwrap();
+
+        let mut process = Memory::new(BASE, data);
+        process.exports.push(("CreateInterface", BASE + CREATE_INTERFACE));
+
+        assert_eq!(read_interfaces(&process, &process.module()).unwrap().len(), MAX_INTERFACES);
+    }
+
+    #[test]
+    fn resolve_factory_leaves_constructed_instances() {
+        let mut data = vec![0u8; 0x100];
+
+        // sub rsp, 0x28; call ...; add rsp, 0x28; ret
+        data[0..16].copy_from_slice(&[0x48, 0x83, 0xEC, 0x28, 0xE8, 0, 0, 0, 0, 0x48, 0x83, 0xC4, 0x28, 0xC3, 0xCC, 0xCC]);
+
+        let process = Memory::new(BASE, data);
+
+        assert_eq!(resolve_factory(&process, BASE).unwrap(), None);
+    }
+}
@@ -1 +1,5 @@
+pub mod interfaces;
 pub mod modules;
+pub mod offsets;
+pub mod schema;
+pub mod vtables;
@@ -1,45 +1,107 @@
-use crate::{config::Config, error::{Error, Result}, platform::{ProcessModule, ProcessTrait}};
-use std::{ffi::OsStr, fs::{create_dir_all, File}, io::{self, Write}, path::Path};
+use crate::{config::Config, error::{Error, Result}, image::{self, pe, Export, ImageFormat, ModuleImage}, game::offsets::Offsets, output::model::Model, platform::{ProcessModule, ProcessTrait}};
+use std::{collections::HashMap, ffi::OsStr, fs::{self, create_dir_all, File}, io::{self, Write}, path::{Path, PathBuf}};
 use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DumpedModule {
+    pub name: String,
+    pub base: usize,
+    pub size: usize,
+    // file name of the image inside the module's output directory
+    pub file: String
+}
+
+// whole module images, read from the process once per run and shared by everything that scans them
+pub struct ModuleImages {
+    images: HashMap<String, Option<(ProcessModule, Vec<u8>)>>
+}
+
+impl ModuleImages {
+    pub fn new() -> Self {
+        Self { images: HashMap::new() }
+    }
+
+    // a module that can't be read is reported the first time it's asked for
+    pub fn get(&mut self, process: &impl ProcessTrait, mod_name: &str) -> Option<&(ProcessModule, Vec<u8>)> {
+        self.images.entry(mod_name.to_string())
+            .or_insert_with(|| match read_module(process, mod_name) {
+                Ok(image) => Some(image),
+                Err(err) => {
+                    println!("failed to read module: {}, error: {}", mod_name, err);
+                    None
+                }
+            })
+            .as_ref()
+    }
+}
+
+fn read_module(process: &impl ProcessTrait, mod_name: &str) -> Result<(ProcessModule, Vec<u8>)> {
+    let module = process.mod_find(mod_name)?;
+
+    let mut data: Vec<u8> = Vec::new();
+    data.resize(module.module_size, b'\0');
+
+    process.mem_read(module.module_base, &mut data)?;
 
-#[cfg(target_os = "windows")]
-use pelite::image::{IMAGE_DOS_HEADER, IMAGE_NT_HEADERS64, IMAGE_OPTIONAL_HEADER64, IMAGE_SECTION_HEADER};
+    Ok((module, data))
+}
 
-pub fn dump(process: &impl ProcessTrait, config: &Config) {
+pub fn dump(process: &impl ProcessTrait, config: &Config, images: &mut ModuleImages) -> Vec<DumpedModule> {
     let modules = match &config.modules {
         Some(modules) => modules,
-        None => return
+        None => return Vec::new()
     };
 
+    let mut dumped = Vec::new();
+
     for mod_name in modules {
-        let module = match process.mod_find(mod_name) {
-            Ok(module) => module,
-            Err(err) => {
-                println!("failed to find module: {}, error: {}", mod_name, err);
-                continue
-            }
+        let (module, data) = match images.get(process, mod_name) {
+            Some(image) => image,
+            None => continue
         };
 
-        match dump_module(process, mod_name, &module) {
-            Ok(_) => println!("dumped module: {} at 0x{:X}", mod_name, module.module_base),
-            Err(err) => {
-                if let Error::IoError(err) = &err {
-                    if err.kind() == io::ErrorKind::AlreadyExists {
-                        println!("module already dumped: {}", mod_name);
-                    }
-                } else {
-                    println!("failed to dump module: {}, error: {}", mod_name, err);
-                }
-            },
+        match dump_module(process, mod_name, module, data.clone()) {
+            Ok(module) => dumped.push(module),
+            Err(err) => println!("failed to dump module: {}, error: {}", mod_name, err)
         }
     }
+
+    dumped
 }
 
-fn write_dump(name: &str, timestamp: DateTime<Utc>, data: &[u8]) -> Result<()> {
-    let mut file_path = Path::new(&format!("output/modules/{}", name)).with_extension("");
+pub fn module_directory(name: &str) -> PathBuf {
+    Path::new(&format!("output/modules/{}", name)).with_extension("")
+}
 
-    create_dir_all(&file_path)?;
+// a dump file given directly, or the latest dump of a module recorded in output/manifest.json
+pub fn find_dump(name: &str) -> Result<PathBuf> {
+    if Path::new(name).is_file() {
+        return Ok(PathBuf::from(name));
+    }
+
+    let model = Model::load(Path::new("output/manifest.json"))?;
+
+    match model.modules.iter().find(|module| module_directory(&module.name) == module_directory(name)) {
+        Some(module) => Ok(module_directory(&module.name).join(&module.file)),
+        None => Err(Error::NotFound)
+    }
+}
+
+fn write_exports(name: &str, exports: &[Export]) -> Result<()> {
+    let directory = module_directory(name);
+
+    create_dir_all(&directory)?;
 
+    Ok(serde_json::to_writer_pretty(
+        File::options()
+            .create(true)
+            .write(true).truncate(true)
+            .open(directory.join("exports.json"))?,
+        exports)?)
+}
+
+fn dump_file_name(name: &str, timestamp: DateTime<Utc>) -> String {
     let file_name = Path::new(name).with_extension("");
     let file_name = file_name.file_name().unwrap().to_str().unwrap();
     let mut file_extension = String::from(Path::new(name).extension().unwrap_or(OsStr::new("")).to_str().unwrap());
@@ -47,15 +109,21 @@ fn write_dump(name: &str, timestamp: DateTime<Utc>, data: &[u8]) -> Result<()> {
         file_extension.insert(0, '.');
     }
 
-    let file_name = format!("{}_{:02}_{:02}_{:04}{}",
+    format!("{}_{:02}_{:02}_{:04}{}",
         file_name, timestamp.day(), timestamp.month(), timestamp.year(), file_extension
-    );
+    )
+}
+
+fn write_dump(name: &str, file_name: &str, data: &[u8]) -> Result<()> {
+    let mut file_path = module_directory(name);
+
+    create_dir_all(&file_path)?;
 
     file_path.push(file_name);
 
     match File::options().create_new(true).write(true).open(&file_path) {
         Ok(mut file) => {
-            file.write(&data)?;
+            file.write_all(data)?;
         },
         Err(err) => {
             return Err(err.into());
@@ -65,82 +133,102 @@ fn write_dump(name: &str, timestamp: DateTime<Utc>, data: &[u8]) -> Result<()> {
     Ok(())
 }
 
-#[cfg(target_os = "windows")]
-unsafe fn get_timestamp(data: &[u8]) -> Result<DateTime<Utc>> {
-    let dos_header = data.as_ptr() as *mut IMAGE_DOS_HEADER;
-    let nt_headers = data.as_ptr().byte_offset((*dos_header).e_lfanew as isize) as *mut IMAGE_NT_HEADERS64;
-    Ok(DateTime::from_timestamp((*nt_headers).FileHeader.TimeDateStamp.into(), 0).unwrap())
-}
+// `data` is the module as it's mapped, fixed up here so it loads like a file
+fn dump_module(process: &impl ProcessTrait, module_name: &str, module: &ProcessModule, mut data: Vec<u8>)
+    -> Result<DumpedModule> {
+
+    image::fix_image(module.module_base, &mut data)?;
+
+    let exports = match ImageFormat::detect(&data)? {
+        ImageFormat::Pe => pe::exports(&ModuleImage { process, module }),
+        ImageFormat::Elf => process.mod_exports(module).map(|exports| exports.into_iter()
+            .map(|export| Export {
+                name: Some(export.name),
+                ordinal: None,
+                rva: export.address - module.module_base,
+                forwarder: None
+            })
+            .collect())
+    };
 
-#[cfg(target_os = "linux")]
-fn get_timestamp(data: &[u8]) -> Result<DateTime<Utc>> {
-    Ok(Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc())
-}
+    if let Err(err) = exports.and_then(|exports| write_exports(module_name, &exports)) {
+        println!("failed to write exports: {}, error: {}", module_name, err);
+    }
 
-fn dump_module(process: &impl ProcessTrait, module_name: &str, module: &ProcessModule)
-    -> Result<()> {
-    
-    let mut data: Vec<u8> = Vec::new();
-    data.resize(module.module_size, b'\0');
+    let file_name = dump_file_name(module_name, image::get_timestamp(&data)?);
 
-    process.mem_read(module.module_base, &mut data)?;
-    
-    unsafe {
-        fix_image(module.module_base, &mut data)?;
-    
-        let timestamp = get_timestamp(&data)?;
-    
-        write_dump(module_name, timestamp, &data)?;
+    // a dump from earlier the same day is the same image, so it's still recorded
+    match write_dump(module_name, &file_name, &data) {
+        Ok(_) => println!("dumped module: {} at 0x{:X}", module_name, module.module_base),
+        Err(Error::IoError(err)) if err.kind() == io::ErrorKind::AlreadyExists => println!("module already dumped: {}", module_name),
+        Err(err) => return Err(err)
     }
-    
-    Ok(())
+
+    Ok(DumpedModule {
+        name: module_name.to_string(),
+        base: module.module_base,
+        size: module.module_size,
+        file: file_name
+    })
 }
 
-#[cfg(target_os = "windows")]
-unsafe fn fix_image(allocation_base: usize, data: &mut [u8]) -> Result<()> {
+// the import is recorded in output/manifest.json like a dumped module, so --dumps and migrate find it
+pub fn import(path: &Path, name: Option<&str>, allocation_base: Option<usize>) -> Result<DumpedModule> {
+    let mut data = fs::read(path)?;
 
-    let dos_header = data.as_mut_ptr() as *mut IMAGE_DOS_HEADER;
-    let nt_headers = data.as_mut_ptr().byte_offset((*dos_header).e_lfanew as isize) as *mut IMAGE_NT_HEADERS64;
+    let name = match name {
+        Some(name) => name,
+        None => match path.file_name().and_then(OsStr::to_str) {
+            Some(name) => name,
+            None => return Err(Error::NotFound)
+        }
+    };
 
-    (*nt_headers).OptionalHeader.ImageBase = allocation_base as u64;
+    let allocation_base = match (allocation_base, ImageFormat::detect(&data)?) {
+        (Some(allocation_base), _) => allocation_base,
+        (None, ImageFormat::Pe) => pe::image_base(&data)?,
+        (None, ImageFormat::Elf) => 0
+    };
 
-    for i in 0..(*nt_headers).FileHeader.NumberOfSections {
-        let section = (nt_headers
-            .byte_offset((std::mem::size_of::<IMAGE_NT_HEADERS64>() - std::mem::size_of::<IMAGE_OPTIONAL_HEADER64>()) as isize)
-            .byte_offset((*nt_headers).FileHeader.SizeOfOptionalHeader as isize) as *mut IMAGE_SECTION_HEADER).offset(i as isize);
+    image::fix_image(allocation_base, &mut data)?;
 
-        (*section).PointerToRawData = (*section).VirtualAddress;
-        (*section).SizeOfRawData = (*section).VirtualSize;
+    if ImageFormat::detect(&data)? == ImageFormat::Pe {
+        write_exports(name, &pe::exports(&data[..])?)?;
     }
 
-    Ok(())
-}
+    let file_name = dump_file_name(name, image::get_timestamp(&data)?);
 
-#[cfg(target_os = "linux")]
-unsafe fn fix_image(_allocation_base: usize, data: &mut [u8]) -> Result<()> {
-    use crate::{error::Error, platform::linux::{Elf64_Ehdr, Elf64_Shdr, ELFMAGIC}};
+    match write_dump(name, &file_name, &data) {
+        Ok(_) => (),
+        Err(Error::IoError(err)) if err.kind() == io::ErrorKind::AlreadyExists => println!("module already dumped: {}", name),
+        Err(err) => return Err(err)
+    }
 
+    let module = DumpedModule {
+        name: name.to_string(),
+        base: allocation_base,
+        size: data.len(),
+        file: file_name
+    };
 
-    if data.len() < std::mem::size_of::<Elf64_Ehdr>() {
-        return Err(Error::InvalidImage);
-    }
+    record_module(&module)?;
 
-    let ehdr = data.as_mut_ptr() as *mut Elf64_Ehdr;
-    
-    if (*ehdr).e_ident[0..4] != ELFMAGIC {
-        return Err(Error::InvalidImage);
-    }
+    Ok(module)
+}
 
-    if (*ehdr).e_shoff == 0 {
-        return Ok(());
-    }
+// replaces an earlier dump of the same module, keeping the rest of the manifest as it was
+fn record_module(module: &DumpedModule) -> Result<()> {
+    let manifest = Path::new("output/manifest.json");
 
-    for i in 0..(*ehdr).e_shnum {
+    let mut model = match manifest.is_file() {
+        true => Model::load(manifest)?,
+        false => Model::new(Vec::new(), Vec::new(), Vec::new(), Vec::new(), Offsets::new())
+    };
 
-        let shdr = (ehdr.byte_offset((*ehdr).e_shoff as isize) as *mut Elf64_Shdr).offset(i as isize);
-        
-        (*shdr).sh_addr = (*shdr).sh_offset;
+    match model.modules.iter_mut().find(|recorded| module_directory(&recorded.name) == module_directory(&module.name)) {
+        Some(recorded) => *recorded = module.clone(),
+        None => model.modules.push(module.clone())
     }
 
-    Ok(())
+    model.save(manifest)
 }
@@ -0,0 +1,268 @@
+use crate::{config::Config, error::{Error, Result}, pattern::Pattern, platform::{ProcessModule, ProcessTrait}};
+use std::{collections::BTreeMap, fmt, fs::{create_dir_all, File}};
+use serde::{Deserialize, Serialize};
+use super::modules::ModuleImages;
+
+// module name -> offset name -> module relative offset or sliced value
+pub type Offsets = BTreeMap<String, BTreeMap<String, Offset>>;
+
+// module relative offsets are plain numbers in offsets.json, sliced values are written as {"value": ...}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Offset {
+    Rva(usize),
+    Value {
+        value: usize
+    }
+}
+
+impl Offset {
+    pub fn get(&self) -> usize {
+        match self {
+            Offset::Rva(rva) => *rva,
+            Offset::Value { value } => *value
+        }
+    }
+}
+
+// generated constants are the same for both
+impl fmt::UpperHex for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::UpperHex::fmt(&self.get(), f)
+    }
+}
+
+impl fmt::Display for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Offset::Rva(rva) => write!(f, "0x{:X}", rva),
+            Offset::Value { value } => write!(f, "0x{:X} (value)", value)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signature {
+    pub module: String,
+    pub pattern: Pattern,
+    #[serde(default)]
+    pub operations: Vec<Operation>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Operation {
+    /// Resolve a rip relative operand, `offset` is where the displacement sits and `length` the instruction size
+    Rip {
+        #[serde(default = "default_rip_offset")]
+        offset: usize,
+        #[serde(default = "default_rip_length")]
+        length: usize
+    },
+    Add {
+        value: isize
+    },
+    Deref,
+    /// Read bytes `start..end` as a little endian immediate, the result is a value instead of an address
+    Slice {
+        start: usize,
+        end: usize
+    }
+}
+
+fn default_rip_offset() -> usize {
+    3
+}
+
+fn default_rip_length() -> usize {
+    7
+}
+
+enum Resolved {
+    Address(usize),
+    Value(usize)
+}
+
+pub fn dump(process: &impl ProcessTrait, config: &Config, images: &mut ModuleImages) -> Offsets {
+    let signatures = match &config.signatures {
+        Some(signatures) if !signatures.is_empty() => signatures,
+        _ => return Offsets::new()
+    };
+
+    let mut offsets = Offsets::new();
+
+    for (name, signature) in signatures {
+        let (module, data) = match images.get(process, &signature.module) {
+            Some(image) => image,
+            None => continue
+        };
+
+        match resolve(process, module, data, name, signature) {
+            Ok(offset) => {
+                match offset {
+                    Offset::Rva(rva) => println!("resolved offset: {} = {}+0x{:X}", name, signature.module, rva),
+                    Offset::Value { value } => println!("resolved value: {} = 0x{:X}", name, value)
+                }
+
+                offsets.entry(signature.module.clone()).or_default().insert(name.clone(), offset);
+            },
+            Err(err) => println!("failed to resolve offset: {}, error: {}", name, err)
+        }
+    }
+
+    match write_offsets(&offsets) {
+        Ok(_) => println!("dumped offsets ({} resolved)", offsets.values().map(|offsets| offsets.len()).sum::<usize>()),
+        Err(err) => println!("failed to write offsets, error: {}", err)
+    }
+
+    offsets
+}
+
+fn resolve(process: &impl ProcessTrait, module: &ProcessModule, data: &[u8], name: &str, signature: &Signature) -> Result<Offset> {
+    let matches = signature.pattern.scan(data);
+
+    let mut resolved = match matches.first() {
+        Some(offset) => Resolved::Address(module.module_base + offset),
+        None => return Err(Error::NotFound)
+    };
+
+    if matches.len() > 1 {
+        println!("pattern for {} matched {} times, using the first match", name, matches.len());
+    }
+
+    for operation in &signature.operations {
+        resolved = match (operation, resolved) {
+            (Operation::Rip { offset, length }, Resolved::Address(address)) => {
+                let displacement: i32 = process.read(address + offset)?;
+                Resolved::Address((address + length).wrapping_add_signed(displacement as isize))
+            },
+            (Operation::Add { value }, Resolved::Address(address)) => Resolved::Address(address.wrapping_add_signed(*value)),
+            (Operation::Add { value }, Resolved::Value(resolved)) => Resolved::Value(resolved.wrapping_add_signed(*value)),
+            (Operation::Deref, Resolved::Address(address)) => Resolved::Address(process.read::<u64>(address)? as usize),
+            (Operation::Slice { start, end }, Resolved::Address(address)) => {
+                if start >= end || end - start > 8 {
+                    return Err(Error::InvalidOperation(format!("slice {}..{}", start, end)));
+                }
+
+                let mut value = [0u8; 8];
+                process.mem_read(address + start, &mut value[..end - start])?;
+                Resolved::Value(u64::from_le_bytes(value) as usize)
+            },
+            (operation, Resolved::Value(_)) => {
+                return Err(Error::InvalidOperation(format!("{:?} on a sliced value", operation)));
+            }
+        };
+    }
+
+    // a pointer into the heap or another module has no meaning relative to this one
+    match resolved {
+        Resolved::Address(address) if (module.module_base..module.module_base + module.module_size).contains(&address) => {
+            Ok(Offset::Rva(address - module.module_base))
+        },
+        Resolved::Address(address) => Err(Error::InvalidOperation(format!("0x{:X} is outside of {}", address, signature.module))),
+        Resolved::Value(value) => Ok(Offset::Value { value })
+    }
+}
+
+fn write_offsets(offsets: &Offsets) -> Result<()> {
+    create_dir_all("output")?;
+
+    Ok(serde_json::to_writer_pretty(
+        File::options()
+            .create(true)
+            .write(true).truncate(true)
+            .open("output/offsets.json")?,
+        offsets)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{image::write, platform::tests::Memory};
+    use super::*;
+
+    const BASE: usize = 0x10000;
+
+    // mov rax, [rip + ...] at 0x10 and 0x30 load the pointers at 0x80 and 0x88, mov ecx, 0x11223344 sits at 0x20
+    fn memory() -> Memory {
+        let mut data = vec![0u8; 0x100];
+
+        data[0x10..0x17].copy_from_slice(&[0x48, 0x8B, 0x05, 0x69, 0x00, 0x00, 0x00]);
+        data[0x20..0x25].copy_from_slice(&[0xB9, 0x44, 0x33, 0x22, 0x11]);
+        data[0x30..0x37].copy_from_slice(&[0x48, 0x8B, 0x05, 0x51, 0x00, 0x00, 0x00]);
+
+        write(&mut data, 0x80, (BASE + 0x90) as u64).unwrap();
+        write(&mut data, 0x88, 0xDEAD0000u64).unwrap();
+
+        Memory::new(BASE, data)
+    }
+
+    fn signature(pattern: &str, operations: Vec<Operation>) -> Signature {
+        Signature {
+            module: "client.dll".to_string(),
+            pattern: pattern.parse().unwrap(),
+            operations
+        }
+    }
+
+    fn resolve_in(process: &Memory, signature: &Signature) -> Result<Offset> {
+        resolve(process, &process.module(), &process.data, "test", signature)
+    }
+
+    #[test]
+    fn resolve_follows_rip_deref_and_add() {
+        let process = memory();
+
+        let cases = [
+            (Vec::new(), Offset::Rva(0x10)),
+            (vec![Operation::Rip { offset: 3, length: 7 }], Offset::Rva(0x80)),
+            (vec![Operation::Rip { offset: 3, length: 7 }, Operation::Deref], Offset::Rva(0x90)),
+            (vec![Operation::Rip { offset: 3, length: 7 }, Operation::Deref, Operation::Add { value: -0x8 }], Offset::Rva(0x88))
+        ];
+
+        for (operations, expected) in cases {
+            assert_eq!(resolve_in(&process, &signature("48 8B 05 69", operations)).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn resolve_slices_values() {
+        let process = memory();
+
+        let sliced = signature("B9 ? ? ? ? 00", vec![Operation::Slice { start: 1, end: 5 }]);
+        assert_eq!(resolve_in(&process, &sliced).unwrap(), Offset::Value { value: 0x11223344 });
+
+        let added = signature("B9 ? ? ? ? 00", vec![Operation::Slice { start: 1, end: 3 }, Operation::Add { value: 1 }]);
+        assert_eq!(resolve_in(&process, &added).unwrap(), Offset::Value { value: 0x3345 });
+
+        for (start, end) in [(3, 3), (0, 9)] {
+            let invalid = signature("B9 ? ? ? ? 00", vec![Operation::Slice { start, end }]);
+            assert!(matches!(resolve_in(&process, &invalid), Err(Error::InvalidOperation(_))));
+        }
+    }
+
+    #[test]
+    fn resolve_rejects_operations_on_sliced_values() {
+        let process = memory();
+
+        for operation in [Operation::Deref, Operation::Rip { offset: 3, length: 7 }, Operation::Slice { start: 0, end: 4 }] {
+            let signature = signature("B9 ? ? ? ? 00", vec![Operation::Slice { start: 1, end: 5 }, operation]);
+            assert!(matches!(resolve_in(&process, &signature), Err(Error::InvalidOperation(_))));
+        }
+    }
+
+    #[test]
+    fn resolve_rejects_addresses_outside_the_module() {
+        let process = memory();
+
+        // the pointer at 0x88 leads out of the module
+        let signature = signature("48 8B 05 51", vec![Operation::Rip { offset: 3, length: 7 }, Operation::Deref]);
+        assert!(matches!(resolve_in(&process, &signature), Err(Error::InvalidOperation(_))));
+    }
+
+    #[test]
+    fn resolve_needs_a_match() {
+        let process = memory();
+
+        assert!(matches!(resolve_in(&process, &signature("CC CC CC", Vec::new())), Err(Error::NotFound)));
+    }
+}
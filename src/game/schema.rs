@@ -0,0 +1,800 @@
+#![allow(dead_code)]
+
+use crate::{error::Result, image::FromBytes, platform::ProcessTrait};
+use super::interfaces::find_interface;
+use std::{collections::BTreeSet, fs::{create_dir_all, File}, mem::size_of, path::Path};
+use serde::{Deserialize, Serialize};
+
+// either one, processes can be read from dumps taken on the other platform
+const SCHEMA_SYSTEM_MODULES: [&str; 2] = ["schemasystem.dll", "libschemasystem.so"];
+
+const SCHEMA_SYSTEM_INTERFACE: &str = "SchemaSystem_001";
+
+const SCHEMA_ENUM_FLAG_IS_FLAGS: u8 = 0x1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaScope {
+    pub name: String,
+    pub classes: Vec<SchemaClass>,
+    pub enums: Vec<SchemaEnum>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaClass {
+    pub name: String,
+    pub module: String,
+    pub size: i32,
+    pub base_classes: Vec<String>,
+    pub fields: Vec<SchemaField>,
+    pub metadata: Vec<SchemaMetadata>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaField {
+    pub name: String,
+    pub offset: i32,
+    pub type_name: String,
+    pub metadata: Vec<SchemaMetadata>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaMetadata {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<SchemaMetadataValue>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SchemaMetadataValue {
+    Int(i32),
+    Float(f32),
+    String(String),
+    VarName {
+        name: String,
+        type_name: String
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaEnum {
+    pub name: String,
+    pub module: String,
+    pub size: u8,
+    pub is_flags: bool,
+    pub enumerators: Vec<SchemaEnumerator>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaEnumerator {
+    pub name: String,
+    pub value: i64
+}
+
+pub fn dump(process: &impl ProcessTrait) -> Vec<SchemaScope> {
+    let module = match SCHEMA_SYSTEM_MODULES.iter().find_map(|name| process.mod_find(name).ok()) {
+        Some(module) => module,
+        None => {
+            println!("failed to find module: {}", SCHEMA_SYSTEM_MODULES.join(" or "));
+            return Vec::new();
+        }
+    };
+
+    let schema_system = match find_interface(process, &module, SCHEMA_SYSTEM_INTERFACE) {
+        Ok(schema_system) => schema_system,
+        Err(err) => {
+            println!("failed to find interface: {}, error: {}", SCHEMA_SYSTEM_INTERFACE, err);
+            return Vec::new();
+        }
+    };
+
+    let type_scopes = match read_type_scopes(process, schema_system) {
+        Ok(type_scopes) => type_scopes,
+        Err(err) => {
+            println!("failed to read schema type scopes, error: {}", err);
+            return Vec::new();
+        }
+    };
+
+    let mut scopes = Vec::new();
+
+    for type_scope in type_scopes {
+        let scope = match read_type_scope(process, type_scope) {
+            Ok(scope) => scope,
+            Err(err) => {
+                println!("failed to read schema type scope at 0x{:X}, error: {}", type_scope, err);
+                continue
+            }
+        };
+
+        match write_scope(&scope) {
+            Ok(_) => println!("dumped schema scope: {} ({} classes, {} enums)",
+                scope.name, scope.classes.len(), scope.enums.len()),
+            Err(err) => println!("failed to write schema scope: {}, error: {}", scope.name, err)
+        }
+
+        scopes.push(scope);
+    }
+
+    scopes
+}
+
+fn write_scope(scope: &SchemaScope) -> Result<()> {
+    let file_path = Path::new("output/schema").join(&scope.name).with_extension("json");
+
+    create_dir_all(file_path.parent().unwrap())?;
+
+    Ok(serde_json::to_writer_pretty(
+        File::options()
+            .create(true)
+            .write(true).truncate(true)
+            .open(&file_path)?,
+        scope)?)
+}
+
+fn read_type_scopes(process: &impl ProcessTrait, schema_system: usize) -> Result<Vec<usize>> {
+    let schema_system: SchemaSystem = process.read(schema_system)?;
+
+    let mut type_scopes: Vec<usize> = Vec::new();
+
+    for i in 0..schema_system.type_scopes.size.max(0) as usize {
+        let type_scope = process.read::<u64>(schema_system.type_scopes.elements as usize + i * 8)? as usize;
+        if type_scope == 0 {
+            continue;
+        }
+
+        let global_scope = process.read::<SchemaSystemTypeScope>(type_scope)?.global_scope as usize;
+        if global_scope != 0 && !type_scopes.contains(&global_scope) {
+            type_scopes.push(global_scope);
+        }
+
+        if !type_scopes.contains(&type_scope) {
+            type_scopes.push(type_scope);
+        }
+    }
+
+    Ok(type_scopes)
+}
+
+fn read_type_scope(process: &impl ProcessTrait, address: usize) -> Result<SchemaScope> {
+    let type_scope: SchemaSystemTypeScope = process.read(address)?;
+
+    let name = &type_scope.name[0..type_scope.name.iter()
+        .position(|c| *c == 0)
+        .unwrap_or(type_scope.name.len())];
+
+    let mut classes: Vec<SchemaClass> = Vec::new();
+
+    for binding in type_scope.class_bindings.elements(process)? {
+        match read_class(process, binding) {
+            Ok(class) => classes.push(class),
+            Err(err) => println!("failed to read schema class at 0x{:X}, error: {}", binding, err)
+        }
+    }
+
+    classes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut enums: Vec<SchemaEnum> = Vec::new();
+
+    for binding in type_scope.enum_bindings.elements(process)? {
+        match read_enum(process, binding) {
+            Ok(schema_enum) => enums.push(schema_enum),
+            Err(err) => println!("failed to read schema enum at 0x{:X}, error: {}", binding, err)
+        }
+    }
+
+    enums.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(SchemaScope {
+        name: std::str::from_utf8(name)?.to_string(),
+        classes,
+        enums
+    })
+}
+
+fn read_class(process: &impl ProcessTrait, address: usize) -> Result<SchemaClass> {
+    let class: SchemaClassInfoData = process.read(address)?;
+
+    let mut base_classes: Vec<String> = Vec::new();
+
+    for i in 0..class.base_class_count as usize {
+        let base_class: SchemaBaseClassInfoData = process.read(class.base_classes as usize + i * size_of::<SchemaBaseClassInfoData>())?;
+        let base_class: SchemaClassInfoData = process.read(base_class.class as usize)?;
+
+        base_classes.push(process.read_string(base_class.name as usize)?);
+    }
+
+    let mut fields: Vec<SchemaField> = Vec::new();
+
+    for i in 0..class.field_count.max(0) as usize {
+        let field: SchemaClassFieldData = process.read(class.fields as usize + i * size_of::<SchemaClassFieldData>())?;
+        let field_type: SchemaType = process.read(field.field_type as usize)?;
+
+        fields.push(SchemaField {
+            name: process.read_string(field.name as usize)?,
+            offset: field.offset,
+            type_name: process.read_string(field_type.name as usize)?,
+            metadata: read_metadata(process, field.metadata as usize, field.metadata_count.max(0) as usize)
+        });
+    }
+
+    Ok(SchemaClass {
+        name: process.read_string(class.name as usize)?,
+        module: process.read_string(class.module_name as usize)?,
+        size: class.size,
+        base_classes,
+        fields,
+        metadata: read_metadata(process, class.static_metadata as usize, class.static_metadata_count.max(0) as usize)
+    })
+}
+
+// an entry that can't be read is left out rather than losing the class it belongs to
+fn read_metadata(process: &impl ProcessTrait, address: usize, count: usize) -> Vec<SchemaMetadata> {
+    let mut metadata: Vec<SchemaMetadata> = Vec::new();
+
+    for i in 0..count {
+        let entry = address + i * size_of::<SchemaMetadataEntryData>();
+
+        match read_metadata_entry(process, entry) {
+            Ok(entry) => metadata.push(entry),
+            Err(err) => println!("failed to read schema metadata at 0x{:X}, error: {}", entry, err)
+        }
+    }
+
+    metadata
+}
+
+fn read_metadata_entry(process: &impl ProcessTrait, address: usize) -> Result<SchemaMetadata> {
+    let entry: SchemaMetadataEntryData = process.read(address)?;
+
+    let name = process.read_string(entry.name as usize)?;
+    let value = match entry.value {
+        0 => None,
+        value => read_metadata_value(process, &name, value as usize)?
+    };
+
+    Ok(SchemaMetadata { name, value })
+}
+
+// the value layout depends on the key, anything not listed here is a marker or has an opaque value
+fn read_metadata_value(process: &impl ProcessTrait, name: &str, address: usize) -> Result<Option<SchemaMetadataValue>> {
+    Ok(match name {
+        "MNetworkChangeCallback" | "MNetworkEncoder" | "MNetworkSerializer" |
+        "MNetworkUserGroup" | "MNetworkAlias" | "MNetworkTypeAlias" |
+        "MNetworkIncludeByName" | "MNetworkExcludeByName" |
+        "MNetworkIncludeByUserGroup" | "MNetworkExcludeByUserGroup" |
+        "MNetworkReplayCompatField" | "MNetworkVarTypeOverride" |
+        "MPropertyFriendlyName" | "MPropertyDescription" | "MPropertyGroupName" |
+        "MPropertyAttributeChoiceName" | "MPropertyStartGroup" => {
+            let string = process.read::<u64>(address)? as usize;
+            Some(SchemaMetadataValue::String(process.read_string(string)?))
+        },
+        "MNetworkBitCount" | "MNetworkPriority" | "MNetworkEncodeFlags" |
+        "MNetworkVarEmbeddedFieldOffsetDelta" | "MPropertySortPriority" => {
+            Some(SchemaMetadataValue::Int(process.read(address)?))
+        },
+        "MNetworkMinValue" | "MNetworkMaxValue" => {
+            Some(SchemaMetadataValue::Float(process.read(address)?))
+        },
+        "MNetworkVarNames" => {
+            let var_name: SchemaVarName = process.read(address)?;
+            Some(SchemaMetadataValue::VarName {
+                name: process.read_string(var_name.name as usize)?,
+                type_name: process.read_string(var_name.type_name as usize)?
+            })
+        },
+        _ => None
+    })
+}
+
+fn read_enum(process: &impl ProcessTrait, address: usize) -> Result<SchemaEnum> {
+    let schema_enum: SchemaEnumInfoData = process.read(address)?;
+
+    let mut enumerators: Vec<SchemaEnumerator> = Vec::new();
+
+    for i in 0..schema_enum.enumerator_count as usize {
+        let enumerator: SchemaEnumeratorInfoData = process.read(schema_enum.enumerators as usize + i * size_of::<SchemaEnumeratorInfoData>())?;
+
+        // the value is stored as a u64, narrow it to the enum's underlying type to recover the sign
+        let value = match schema_enum.size {
+            1 => enumerator.value as i8 as i64,
+            2 => enumerator.value as i16 as i64,
+            4 => enumerator.value as i32 as i64,
+            _ => enumerator.value as i64
+        };
+
+        enumerators.push(SchemaEnumerator {
+            name: process.read_string(enumerator.name as usize)?,
+            value
+        });
+    }
+
+    Ok(SchemaEnum {
+        name: process.read_string(schema_enum.name as usize)?,
+        module: process.read_string(schema_enum.module_name as usize)?,
+        size: schema_enum.size,
+        is_flags: schema_enum.flags & SCHEMA_ENUM_FLAG_IS_FLAGS != 0,
+        enumerators
+    })
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct UtlVector {
+    size: i32,                      // 0x0000
+    pad_0004: [u8; 0x4],            // 0x0004
+    elements: u64,                  // 0x0008
+    allocation_count: i32,          // 0x0010
+    grow_size: i32                  // 0x0014
+}
+
+unsafe impl FromBytes for UtlVector {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct UtlMemoryPool {
+    block_size: i32,                // 0x0000
+    blocks_per_blob: i32,           // 0x0004
+    grow_mode: i32,                 // 0x0008
+    blocks_allocated: i32,          // 0x000C
+    peak_allocated: i32,            // 0x0010
+    alignment: u16,                 // 0x0014
+    blob_count: u16,                // 0x0016
+    pad_0018: [u8; 0x40],           // 0x0018
+    blob_head: u64,                 // 0x0058
+    total_size: i32,                // 0x0060
+    pad_0064: [u8; 0x1C]            // 0x0064
+}
+
+unsafe impl FromBytes for UtlMemoryPool {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct HashAllocatedBlob {
+    next: u64,                      // 0x0000
+    pad_0008: [u8; 0x8],            // 0x0008
+    data: u64,                      // 0x0010
+    pad_0018: [u8; 0x8]             // 0x0018
+}
+
+unsafe impl FromBytes for HashAllocatedBlob {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct HashBucket {
+    pad_0000: [u8; 0x18],           // 0x0000
+    first: u64,                     // 0x0018
+    first_uncommitted: u64          // 0x0020
+}
+
+unsafe impl FromBytes for HashBucket {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct HashFixedDataInternal {
+    key: u64,                       // 0x0000
+    next: u64,                      // 0x0008
+    data: u64                       // 0x0010
+}
+
+unsafe impl FromBytes for HashFixedDataInternal {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct UtlTsHash {
+    entry_memory: UtlMemoryPool,    // 0x0000
+    buckets: [HashBucket; 256],     // 0x0080
+    needs_commit: u8                // 0x2880
+}
+
+unsafe impl FromBytes for UtlTsHash {}
+
+impl UtlTsHash {
+    fn elements(&self, process: &impl ProcessTrait) -> Result<Vec<usize>> {
+        let limit = self.entry_memory.peak_allocated.max(self.entry_memory.blocks_allocated).max(0) as usize;
+
+        let mut elements: BTreeSet<u64> = BTreeSet::new();
+
+        for bucket in &self.buckets {
+            let mut element = bucket.first_uncommitted;
+            let mut count = 0;
+
+            while element != 0 && count < limit {
+                let node: HashFixedDataInternal = process.read(element as usize)?;
+                if node.data != 0 {
+                    elements.insert(node.data);
+                }
+
+                element = node.next;
+                count += 1;
+            }
+        }
+
+        let mut blob = self.entry_memory.blob_head;
+        let mut count = 0;
+
+        while blob != 0 && count < limit {
+            let node: HashAllocatedBlob = process.read(blob as usize)?;
+            if node.data != 0 {
+                elements.insert(node.data);
+            }
+
+            blob = node.next;
+            count += 1;
+        }
+
+        Ok(elements.into_iter().map(|element| element as usize).collect())
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SchemaSystem {
+    pad_0000: [u8; 0x190],          // 0x0000
+    type_scopes: UtlVector          // 0x0190
+}
+
+unsafe impl FromBytes for SchemaSystem {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SchemaSystemTypeScope {
+    vtable: u64,                    // 0x0000
+    name: [u8; 256],                // 0x0008
+    global_scope: u64,              // 0x0108
+    pad_0110: [u8; 0x450],          // 0x0110
+    class_bindings: UtlTsHash,      // 0x0560
+    enum_bindings: UtlTsHash        // 0x2DE8
+}
+
+unsafe impl FromBytes for SchemaSystemTypeScope {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SchemaClassInfoData {
+    this: u64,                      // 0x0000
+    name: u64,                      // 0x0008
+    module_name: u64,               // 0x0010
+    size: i32,                      // 0x0018
+    field_count: i16,               // 0x001C
+    static_field_count: i16,        // 0x001E
+    static_metadata_count: i16,     // 0x0020
+    alignment: u8,                  // 0x0022
+    base_class_count: u8,           // 0x0023
+    multiple_inheritance_depth: i16,// 0x0024
+    single_inheritance_depth: i16,  // 0x0026
+    fields: u64,                    // 0x0028
+    static_fields: u64,             // 0x0030
+    base_classes: u64,              // 0x0038
+    field_metadata_overrides: u64,  // 0x0040
+    static_metadata: u64,           // 0x0048
+    type_scope: u64,                // 0x0050
+    schema_type: u64,               // 0x0058
+    flags: u64                      // 0x0060
+}
+
+unsafe impl FromBytes for SchemaClassInfoData {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SchemaBaseClassInfoData {
+    offset: u32,                    // 0x0000
+    pad_0004: [u8; 0x4],            // 0x0004
+    class: u64                      // 0x0008
+}
+
+unsafe impl FromBytes for SchemaBaseClassInfoData {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SchemaClassFieldData {
+    name: u64,                      // 0x0000
+    field_type: u64,                // 0x0008
+    offset: i32,                    // 0x0010
+    metadata_count: i32,            // 0x0014
+    metadata: u64                   // 0x0018
+}
+
+unsafe impl FromBytes for SchemaClassFieldData {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SchemaMetadataEntryData {
+    name: u64,                      // 0x0000
+    value: u64                      // 0x0008
+}
+
+unsafe impl FromBytes for SchemaMetadataEntryData {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SchemaVarName {
+    name: u64,                      // 0x0000
+    type_name: u64                  // 0x0008
+}
+
+unsafe impl FromBytes for SchemaVarName {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SchemaEnumInfoData {
+    this: u64,                      // 0x0000
+    name: u64,                      // 0x0008
+    module_name: u64,               // 0x0010
+    size: u8,                       // 0x0018
+    alignment: u8,                  // 0x0019
+    flags: u8,                      // 0x001A
+    pad_001b: [u8; 0x1],            // 0x001B
+    enumerator_count: u16,          // 0x001C
+    static_metadata_count: u16,     // 0x001E
+    enumerators: u64,               // 0x0020
+    static_metadata: u64,           // 0x0028
+    type_scope: u64,                // 0x0030
+    min_enumerator_value: i64,      // 0x0038
+    max_enumerator_value: i64       // 0x0040
+}
+
+unsafe impl FromBytes for SchemaEnumInfoData {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SchemaEnumeratorInfoData {
+    name: u64,                      // 0x0000
+    value: u64,                     // 0x0008
+    metadata_count: i32,            // 0x0010
+    pad_0014: [u8; 0x4],            // 0x0014
+    metadata: u64                   // 0x0018
+}
+
+unsafe impl FromBytes for SchemaEnumeratorInfoData {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SchemaType {
+    vtable: u64,                    // 0x0000
+    name: u64,                      // 0x0008
+    type_scope: u64,                // 0x0010
+    type_category: u8,              // 0x0018
+    atomic_category: u8             // 0x0019
+}
+
+unsafe impl FromBytes for SchemaType {}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::{image::{read, write}, platform::tests::Memory};
+    use super::*;
+
+    const BASE: usize = 0x10000;
+
+    // somewhere the test memory doesn't cover
+    const UNMAPPED: u64 = 0xDEAD0000;
+
+    fn string(data: &mut [u8], offset: usize, value: &str) -> u64 {
+        data[offset..offset + value.len()].copy_from_slice(value.as_bytes());
+        data[offset + value.len()] = 0;
+        (BASE + offset) as u64
+    }
+
+    fn zeroed<T: FromBytes>() -> T {
+        read(&vec![0u8; size_of::<T>()], 0).unwrap()
+    }
+
+    #[test]
+    fn read_type_scopes_adds_global_scopes_once() {
+        const SCOPE: usize = 0x1000;
+        const GLOBAL: usize = 0x7000;
+        const ELEMENTS: usize = 0x800;
+
+        let mut data = vec![0u8; 0xD000];
+
+        let mut schema_system: SchemaSystem = zeroed();
+        schema_system.type_scopes.size = 4;
+        schema_system.type_scopes.elements = (BASE + ELEMENTS) as u64;
+        write(&mut data, 0, schema_system).unwrap();
+
+        for (i, type_scope) in [SCOPE, 0, GLOBAL, SCOPE].into_iter().enumerate() {
+            let type_scope = match type_scope {
+                0 => 0,
+                offset => (BASE + offset) as u64
+            };
+            write(&mut data, ELEMENTS + i * 8, type_scope).unwrap();
+        }
+
+        let mut type_scope: SchemaSystemTypeScope = zeroed();
+        type_scope.global_scope = (BASE + GLOBAL) as u64;
+        write(&mut data, SCOPE, type_scope).unwrap();
+        write(&mut data, GLOBAL, type_scope).unwrap();
+
+        let process = Memory::new(BASE, data);
+
+        assert_eq!(read_type_scopes(&process, BASE).unwrap(), [BASE + GLOBAL, BASE + SCOPE]);
+    }
+
+    #[test]
+    fn elements_walks_buckets_and_blobs() {
+        let mut data = vec![0u8; 0x1000];
+
+        let nodes = [(0x100, 0xA000, 0x120), (0x120, 0xB000, 0), (0x140, 0xC000, 0)];
+        for (offset, element, next) in nodes {
+            let next = match next {
+                0 => 0,
+                next => (BASE + next) as u64
+            };
+            write(&mut data, offset, HashFixedDataInternal { key: 0, next, data: element }).unwrap();
+        }
+
+        // the blob list holds committed elements, some also still sit in a bucket
+        write(&mut data, 0x200, HashAllocatedBlob { next: (BASE + 0x220) as u64, pad_0008: [0; 8], data: 0xA000, pad_0018: [0; 8] }).unwrap();
+        write(&mut data, 0x220, HashAllocatedBlob { next: 0, pad_0008: [0; 8], data: 0xD000, pad_0018: [0; 8] }).unwrap();
+
+        let mut hash: UtlTsHash = zeroed();
+        hash.entry_memory.peak_allocated = 4;
+        hash.entry_memory.blob_head = (BASE + 0x200) as u64;
+        hash.buckets[3].first_uncommitted = (BASE + 0x100) as u64;
+        hash.buckets[200].first_uncommitted = (BASE + 0x140) as u64;
+
+        let process = Memory::new(BASE, data);
+
+        assert_eq!(hash.elements(&process).unwrap(), [0xA000, 0xB000, 0xC000, 0xD000]);
+    }
+
+    #[test]
+    fn elements_stops_at_the_allocated_count() {
+        let mut data = vec![0u8; 0x1000];
+
+        // a node linked to itself would be walked forever
+        write(&mut data, 0x100, HashFixedDataInternal { key: 0, next: (BASE + 0x100) as u64, data: 0xA000 }).unwrap();
+
+        let mut hash: UtlTsHash = zeroed();
+        hash.entry_memory.blocks_allocated = 2;
+        hash.buckets[0].first_uncommitted = (BASE + 0x100) as u64;
+
+        let process = Memory::new(BASE, data);
+
+        assert_eq!(hash.elements(&process).unwrap(), [0xA000]);
+    }
+
+    #[test]
+    fn read_class_reads_fields_and_skips_broken_metadata() {
+        const FIELDS: usize = 0x200;
+        const METADATA: usize = 0x300;
+        const BASE_CLASSES: usize = 0x380;
+        const BASE_CLASS: usize = 0x400;
+        const TYPES: usize = 0x500;
+
+        let mut data = vec![0u8; 0x1000];
+
+        let mut class: SchemaClassInfoData = zeroed();
+        class.name = string(&mut data, 0x800, "C_BaseEntity");
+        class.module_name = string(&mut data, 0x820, "client.dll");
+        class.size = 0x40;
+        class.field_count = 2;
+        class.fields = (BASE + FIELDS) as u64;
+        class.base_class_count = 1;
+        class.base_classes = (BASE + BASE_CLASSES) as u64;
+        write(&mut data, 0, class).unwrap();
+
+        let mut base_class: SchemaClassInfoData = zeroed();
+        base_class.name = string(&mut data, 0x840, "CEntityInstance");
+        write(&mut data, BASE_CLASS, base_class).unwrap();
+        write(&mut data, BASE_CLASSES, SchemaBaseClassInfoData { offset: 0, pad_0004: [0; 4], class: (BASE + BASE_CLASS) as u64 }).unwrap();
+
+        let types = [string(&mut data, 0x860, "int32"), string(&mut data, 0x870, "Vector")];
+        for (i, name) in types.into_iter().enumerate() {
+            let mut schema_type: SchemaType = zeroed();
+            schema_type.name = name;
+            write(&mut data, TYPES + i * size_of::<SchemaType>(), schema_type).unwrap();
+        }
+
+        let fields = [("m_iHealth", 0x10, 0), ("m_vecOrigin", 0x14, 2)];
+        for (i, (name, offset, metadata_count)) in fields.into_iter().enumerate() {
+            let field = SchemaClassFieldData {
+                name: string(&mut data, 0x880 + i * 0x20, name),
+                field_type: (BASE + TYPES + i * size_of::<SchemaType>()) as u64,
+                offset,
+                metadata_count,
+                metadata: (BASE + METADATA) as u64
+            };
+            write(&mut data, FIELDS + i * size_of::<SchemaClassFieldData>(), field).unwrap();
+        }
+
+        // the second entry's name can't be read, only that entry is dropped
+        let networked = string(&mut data, 0x8C0, "MNetworkEnable");
+        write(&mut data, METADATA, SchemaMetadataEntryData { name: networked, value: 0 }).unwrap();
+        write(&mut data, METADATA + size_of::<SchemaMetadataEntryData>(), SchemaMetadataEntryData { name: UNMAPPED, value: 0 }).unwrap();
+
+        let process = Memory::new(BASE, data);
+
+        let class = read_class(&process, BASE).unwrap();
+
+        assert_eq!(class.name, "C_BaseEntity");
+        assert_eq!(class.module, "client.dll");
+        assert_eq!(class.size, 0x40);
+        assert_eq!(class.base_classes, ["CEntityInstance"]);
+
+        let fields: Vec<(&str, i32, &str)> = class.fields.iter()
+            .map(|field| (field.name.as_str(), field.offset, field.type_name.as_str()))
+            .collect();
+
+        assert_eq!(fields, [("m_iHealth", 0x10, "int32"), ("m_vecOrigin", 0x14, "Vector")]);
+
+        let metadata: Vec<&str> = class.fields[1].metadata.iter().map(|metadata| metadata.name.as_str()).collect();
+
+        assert_eq!(metadata, ["MNetworkEnable"]);
+    }
+
+    #[test]
+    fn read_enum_narrows_values_to_the_enum_size() {
+        let cases: [(u8, u64, i64); 8] = [
+            (1, 0xFF, -1),
+            (1, 0x7F, 0x7F),
+            (2, 0xFFFF, -1),
+            (2, 0x8000, -0x8000),
+            (4, 0xFFFF_FFFF, -1),
+            (4, 0x7FFF_FFFF, 0x7FFF_FFFF),
+            (8, u64::MAX, -1),
+            (8, 0x1_0000_0000, 0x1_0000_0000)
+        ];
+
+        for (size, stored, expected) in cases {
+            let mut data = vec![0u8; 0x1000];
+
+            let mut schema_enum: SchemaEnumInfoData = zeroed();
+            schema_enum.name = string(&mut data, 0x800, "EFlags");
+            schema_enum.module_name = string(&mut data, 0x820, "client.dll");
+            schema_enum.size = size;
+            schema_enum.flags = SCHEMA_ENUM_FLAG_IS_FLAGS;
+            schema_enum.enumerator_count = 1;
+            schema_enum.enumerators = (BASE + 0x100) as u64;
+            write(&mut data, 0, schema_enum).unwrap();
+
+            let mut enumerator: SchemaEnumeratorInfoData = zeroed();
+            enumerator.name = string(&mut data, 0x840, "FL_VALUE");
+            enumerator.value = stored;
+            write(&mut data, 0x100, enumerator).unwrap();
+
+            let process = Memory::new(BASE, data);
+
+            let schema_enum = read_enum(&process, BASE).unwrap();
+
+            assert_eq!(schema_enum.name, "EFlags");
+            assert_eq!(schema_enum.size, size);
+            assert!(schema_enum.is_flags);
+            assert_eq!(schema_enum.enumerators[0].name, "FL_VALUE");
+            assert_eq!(schema_enum.enumerators[0].value, expected, "size {} value 0x{:X}", size, stored);
+        }
+    }
+
+    #[test]
+    fn read_metadata_value_by_key() {
+        const VALUE: usize = 0x100;
+
+        let mut data = vec![0u8; 0x1000];
+
+        let group = string(&mut data, 0x800, "Attributes");
+        let var_name = string(&mut data, 0x820, "m_flValue");
+        let type_name = string(&mut data, 0x840, "float32");
+
+        // values are compared by how they end up in the output
+        let cases: [(&str, Vec<u8>, serde_json::Value); 6] = [
+            ("MPropertyGroupName", group.to_le_bytes().to_vec(), json!("Attributes")),
+            ("MNetworkBitCount", (-12i32).to_le_bytes().to_vec(), json!(-12)),
+            ("MNetworkMaxValue", 1.5f32.to_le_bytes().to_vec(), json!(1.5)),
+            ("MNetworkVarNames", [var_name.to_le_bytes(), type_name.to_le_bytes()].concat(), json!({ "name": "m_flValue", "type_name": "float32" })),
+            ("MNetworkEnable", vec![0xFF; 8], json!(null)),
+            ("MUnknownKey", vec![0xFF; 8], json!(null))
+        ];
+
+        for (name, value, expected) in cases {
+            data[VALUE..VALUE + value.len()].copy_from_slice(&value);
+
+            let process = Memory::new(BASE, data.clone());
+
+            let value = read_metadata_value(&process, name, BASE + VALUE).unwrap();
+
+            assert_eq!(serde_json::to_value(value).unwrap(), expected, "{}", name);
+        }
+    }
+}
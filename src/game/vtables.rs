@@ -0,0 +1,61 @@
+use crate::{config::Config, error::Result, image::rtti::{self, VTable}, platform::ProcessTrait};
+use std::{fs::{create_dir_all, File}, path::Path};
+use serde::{Deserialize, Serialize};
+use super::modules::ModuleImages;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleVTables {
+    pub module: String,
+    pub vtables: Vec<VTable>
+}
+
+pub fn dump(process: &impl ProcessTrait, config: &Config, images: &mut ModuleImages) -> Vec<ModuleVTables> {
+    let modules = match &config.modules {
+        Some(modules) => modules,
+        None => return Vec::new()
+    };
+
+    let mut dumped = Vec::new();
+
+    for mod_name in modules {
+        let (module, data) = match images.get(process, mod_name) {
+            Some(image) => image,
+            None => continue
+        };
+
+        let vtables = match rtti::vtables(data, module.module_base) {
+            Ok(vtables) => vtables,
+            Err(err) => {
+                println!("failed to find vtables: {}, error: {}", mod_name, err);
+                continue
+            }
+        };
+
+        let vtables = ModuleVTables {
+            module: mod_name.clone(),
+            vtables
+        };
+
+        match write_vtables(&vtables) {
+            Ok(_) => println!("dumped vtables: {} ({} vtables)", mod_name, vtables.vtables.len()),
+            Err(err) => println!("failed to write vtables: {}, error: {}", mod_name, err)
+        }
+
+        dumped.push(vtables);
+    }
+
+    dumped
+}
+
+fn write_vtables(vtables: &ModuleVTables) -> Result<()> {
+    let file_path = Path::new("output/vtables").join(&vtables.module).with_extension("json");
+
+    create_dir_all(file_path.parent().unwrap())?;
+
+    Ok(serde_json::to_writer_pretty(
+        File::options()
+            .create(true)
+            .write(true).truncate(true)
+            .open(&file_path)?,
+        vtables)?)
+}
@@ -0,0 +1,803 @@
+#![allow(dead_code, non_camel_case_types)]
+
+use std::{collections::{HashMap, HashSet}, ffi::c_uchar, mem::size_of};
+use chrono::{DateTime, Utc};
+use crate::{error::{Error, Result}, platform::{ProcessExport, ProcessModule, ProcessTrait}};
+use super::{read, write, FromBytes, Section};
+
+pub fn fix_image(data: &mut [u8]) -> Result<()> {
+    let ehdr: Elf64_Ehdr = read(data, 0)?;
+
+    if ehdr.e_ident[0..4] != ELFMAGIC {
+        return Err(Error::InvalidImage);
+    }
+
+    // section headers usually sit past the last loaded segment and aren't part of the image,
+    // when they are loaded they're at their mapped address, not at their file offset
+    let shdrs_size = ehdr.e_shnum as usize * size_of::<Elf64_Shdr>();
+    if ehdr.e_shoff == 0 || shdrs_size == 0 {
+        return Ok(());
+    }
+
+    let shoff = ehdr.e_shoff as usize;
+
+    let mut shdrs_rva = None;
+
+    for i in 0..ehdr.e_phnum as usize {
+        let phdr: Elf64_Phdr = read(data, ehdr.e_phoff as usize + i * ehdr.e_phentsize as usize)?;
+
+        if phdr.p_type == PT_LOAD && phdr.p_offset as usize <= shoff && shoff + shdrs_size <= (phdr.p_offset + phdr.p_filesz) as usize {
+            shdrs_rva = Some(phdr.p_vaddr as usize + shoff - phdr.p_offset as usize);
+            break;
+        }
+    }
+
+    let shdrs_rva = match shdrs_rva {
+        Some(shdrs_rva) if shdrs_rva + shdrs_size <= data.len() => shdrs_rva,
+        _ => return Ok(())
+    };
+
+    for i in 0..ehdr.e_shnum as usize {
+        let offset = shdrs_rva + i * size_of::<Elf64_Shdr>();
+
+        let mut shdr: Elf64_Shdr = read(data, offset)?;
+        shdr.sh_addr = shdr.sh_offset;
+        write(data, offset, shdr)?;
+    }
+
+    Ok(())
+}
+
+// ELF images carry no link timestamp, so dumps are dated by the day they were taken
+pub fn get_timestamp(_data: &[u8]) -> Result<DateTime<Utc>> {
+    Ok(Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc())
+}
+
+// sections from the section headers when the image has them mapped, loadable segments named by their
+// permissions otherwise, section headers usually sit past the last segment and aren't part of a mapped image
+pub fn sections(data: &[u8]) -> Result<Vec<Section>> {
+    let ehdr: Elf64_Ehdr = read(data, 0)?;
+
+    if ehdr.e_ident[0..4] != ELFMAGIC {
+        return Err(Error::InvalidImage);
+    }
+
+    let mut loads: Vec<Elf64_Phdr> = Vec::new();
+
+    for i in 0..ehdr.e_phnum as usize {
+        let phdr: Elf64_Phdr = read(data, ehdr.e_phoff as usize + i * ehdr.e_phentsize as usize)?;
+
+        if phdr.p_type == PT_LOAD {
+            loads.push(phdr);
+        }
+    }
+
+    if let Some(sections) = named_sections(data, &ehdr, &loads).filter(|sections| !sections.is_empty()) {
+        return Ok(sections);
+    }
+
+    // segments keep their permissions between builds while their count and order may not
+    Ok(loads.iter()
+        .map(|phdr| {
+            let permissions: String = [(PF_R, 'R'), (PF_W, 'W'), (PF_X, 'X')].iter()
+                .filter(|(flag, _)| phdr.p_flags & flag != 0)
+                .map(|(_, c)| c)
+                .collect();
+
+            Section {
+                name: format!("LOAD_{}", permissions),
+                rva: phdr.p_vaddr as usize,
+                size: phdr.p_memsz as usize,
+                executable: phdr.p_flags & PF_X != 0
+            }
+        })
+        .collect())
+}
+
+// allocated sections, located through their file offsets since dumps rewrite sh_addr, see fix_image
+fn named_sections(data: &[u8], ehdr: &Elf64_Ehdr, loads: &[Elf64_Phdr]) -> Option<Vec<Section>> {
+    if ehdr.e_shoff == 0 || ehdr.e_shstrndx >= ehdr.e_shnum {
+        return None;
+    }
+
+    let shdrs_size = (ehdr.e_shnum as usize).checked_mul(size_of::<Elf64_Shdr>())?;
+    let shdrs_rva = mapped_rva(loads, ehdr.e_shoff, shdrs_size as u64)?;
+
+    let shdrs: Vec<Elf64_Shdr> = (0..ehdr.e_shnum as usize)
+        .map(|i| read(data, shdrs_rva + i * size_of::<Elf64_Shdr>()))
+        .collect::<Result<_>>()
+        .ok()?;
+
+    let shstrtab = shdrs[ehdr.e_shstrndx as usize];
+    let shstrtab_rva = mapped_rva(loads, shstrtab.sh_offset, shstrtab.sh_size)?;
+    let names = data.get(shstrtab_rva..shstrtab_rva.checked_add(shstrtab.sh_size as usize)?)?;
+
+    let mut sections: Vec<Section> = Vec::new();
+
+    for shdr in shdrs.iter().filter(|shdr| shdr.sh_flags & SHF_ALLOC != 0 && shdr.sh_flags & SHF_TLS == 0 && shdr.sh_size != 0) {
+        // .bss and friends take no room in the file, only their start has to be inside a segment
+        let size = match shdr.sh_type {
+            SHT_NOBITS => 0,
+            _ => shdr.sh_size
+        };
+
+        let name = names.get(shdr.sh_name as usize..)?;
+        let name = std::str::from_utf8(&name[..name.iter().position(|c| *c == 0)?]).ok()?;
+
+        sections.push(Section {
+            name: name.to_string(),
+            rva: mapped_rva(loads, shdr.sh_offset, size)?,
+            size: shdr.sh_size as usize,
+            executable: shdr.sh_flags & SHF_EXECINSTR != 0
+        });
+    }
+
+    Some(sections)
+}
+
+// where `size` bytes at a file offset ended up in the image
+fn mapped_rva(loads: &[Elf64_Phdr], offset: u64, size: u64) -> Option<usize> {
+    let end = offset.checked_add(size)?;
+
+    loads.iter()
+        .find(|phdr| phdr.p_offset <= offset && end <= phdr.p_offset.saturating_add(phdr.p_filesz))
+        .and_then(|phdr| phdr.p_vaddr.checked_add(offset - phdr.p_offset))
+        .map(|rva| rva as usize)
+}
+
+// what the loader wrote for absolute relocations against any of `names`, read from the image as it was
+// mapped at `base`. e.g. the vtable pointers of typeinfo objects, which point into the C++ runtime
+pub fn relocated_values(data: &[u8], base: usize, names: &[&str]) -> Result<HashSet<usize>> {
+    let ehdr: Elf64_Ehdr = read(data, 0)?;
+
+    let mut phdrs: Vec<Elf64_Phdr> = Vec::new();
+    for i in 0..ehdr.e_phnum as usize {
+        phdrs.push(read(data, ehdr.e_phoff as usize + i * size_of::<Elf64_Phdr>())?);
+    }
+
+    let first_load = match phdrs.iter().filter(|phdr| phdr.p_type == PT_LOAD).map(|phdr| phdr.p_vaddr).min() {
+        Some(vaddr) => vaddr as usize & !0xFFF,
+        None => return Err(Error::InvalidImage)
+    };
+
+    let mut values: HashSet<usize> = HashSet::new();
+
+    let dynamic = match phdrs.iter().find(|phdr| phdr.p_type == PT_DYNAMIC) {
+        Some(dynamic) => dynamic,
+        None => return Ok(values)
+    };
+
+    // d_ptr entries may or may not have been relocated, see DynamicSymbols::read
+    let rva = |ptr: usize| match ptr < base {
+        true => ptr.wrapping_sub(first_load),
+        false => ptr - base
+    };
+
+    let dynamic_offset = (dynamic.p_vaddr as usize).checked_sub(first_load).ok_or(Error::InvalidImage)?;
+
+    let mut rela = None;
+    let mut rela_size = 0;
+    let mut symtab = None;
+    let mut strtab = None;
+
+    for i in 0..dynamic.p_memsz as usize / size_of::<Elf64_Dyn>() {
+        let dyn_entry: Elf64_Dyn = read(data, dynamic_offset + i * size_of::<Elf64_Dyn>())?;
+
+        match dyn_entry.d_tag {
+            DT_NULL => break,
+            DT_RELA => rela = Some(rva(dyn_entry.d_val as usize)),
+            DT_RELASZ => rela_size = dyn_entry.d_val as usize,
+            DT_SYMTAB => symtab = Some(rva(dyn_entry.d_val as usize)),
+            DT_STRTAB => strtab = Some(rva(dyn_entry.d_val as usize)),
+            _ => ()
+        }
+    }
+
+    let (rela, symtab, strtab) = match (rela, symtab, strtab) {
+        (Some(rela), Some(symtab), Some(strtab)) => (rela, symtab, strtab),
+        _ => return Ok(values)
+    };
+
+    // symbol index -> whether it's one of `names`
+    let mut matching: HashMap<usize, bool> = HashMap::new();
+
+    for i in 0..rela_size / size_of::<Elf64_Rela>() {
+        let relocation: Elf64_Rela = read(data, rela + i * size_of::<Elf64_Rela>())?;
+
+        let symbol = (relocation.r_info >> 32) as usize;
+
+        if relocation.r_info as Elf64_Word != R_X86_64_64 || symbol == 0 {
+            continue;
+        }
+
+        let is_match = match matching.get(&symbol) {
+            Some(is_match) => *is_match,
+            None => {
+                let sym: Elf64_Sym = read(data, symtab + symbol * size_of::<Elf64_Sym>())?;
+                let name = data.get(strtab + sym.st_name as usize..)
+                    .and_then(|name| name.split(|byte| *byte == 0).next())
+                    .ok_or(Error::InvalidImage)?;
+
+                *matching.entry(symbol).or_insert(names.iter().any(|wanted| wanted.as_bytes() == name))
+            }
+        };
+
+        if is_match {
+            values.insert(read::<u64>(data, rva(relocation.r_offset as usize))? as usize);
+        }
+    }
+
+    Ok(values)
+}
+
+pub const ELFMAG0: u8 = 0x7f;
+pub const ELFMAG1: u8 = 0x45;
+pub const ELFMAG2: u8 = 0x4c;
+pub const ELFMAG3: u8 = 0x46;
+pub const ELFMAGIC: [u8; 4] = [ELFMAG0, ELFMAG1, ELFMAG2, ELFMAG3];
+
+pub const ET_CORE: Elf64_Half = 4;
+
+pub const PT_LOAD: Elf64_Word = 1;
+pub const PT_DYNAMIC: Elf64_Word = 2;
+pub const PT_NOTE: Elf64_Word = 4;
+
+pub const NT_FILE: Elf64_Word = 0x46494c45;
+
+pub const PF_X: Elf64_Word = 1;
+pub const PF_W: Elf64_Word = 2;
+pub const PF_R: Elf64_Word = 4;
+
+pub const SHT_NOBITS: Elf64_Word = 8;
+
+pub const SHF_ALLOC: Elf64_Xword = 2;
+pub const SHF_EXECINSTR: Elf64_Xword = 4;
+pub const SHF_TLS: Elf64_Xword = 0x400;
+
+pub const DT_NULL: Elf64_Sxword = 0;
+pub const DT_HASH: Elf64_Sxword = 4;
+pub const DT_STRTAB: Elf64_Sxword = 5;
+pub const DT_SYMTAB: Elf64_Sxword = 6;
+pub const DT_RELA: Elf64_Sxword = 7;
+pub const DT_RELASZ: Elf64_Sxword = 8;
+pub const DT_GNU_HASH: Elf64_Sxword = 0x6ffffef5;
+
+pub const SHN_UNDEF: Elf64_Half = 0;
+
+pub const R_X86_64_64: Elf64_Word = 1;
+
+pub type Elf64_Half = u16;
+pub type Elf64_Addr = u64;
+pub type Elf64_Off = u64;
+pub type Elf64_Word = u32;
+pub type Elf64_Xword = u64;
+pub type Elf64_Sxword = i64;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Elf64_Ehdr {
+
+    pub e_ident: [c_uchar; 16],
+    pub e_type: Elf64_Half,
+    pub e_machine: Elf64_Half,
+    pub e_version: Elf64_Word,
+    pub e_entry: Elf64_Addr,
+    pub e_phoff: Elf64_Off,
+    pub e_shoff: Elf64_Off,
+    pub e_flags: Elf64_Word,
+    pub e_ehsize: Elf64_Half,
+    pub e_phentsize: Elf64_Half,
+    pub e_phnum: Elf64_Half,
+    pub e_shentsize: Elf64_Half,
+    pub e_shnum: Elf64_Half,
+    pub e_shstrndx: Elf64_Half,
+}
+
+unsafe impl FromBytes for Elf64_Ehdr {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Elf64_Shdr {
+    pub sh_name: Elf64_Word,
+    pub sh_type: Elf64_Word,
+    pub sh_flags: Elf64_Xword,
+    pub sh_addr: Elf64_Addr,
+    pub sh_offset: Elf64_Off,
+    pub sh_size: Elf64_Xword,
+    pub sh_link: Elf64_Word,
+    pub sh_info: Elf64_Word,
+    pub sh_addralign: Elf64_Xword,
+    pub sh_entsize: Elf64_Xword,
+}
+
+unsafe impl FromBytes for Elf64_Shdr {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Elf64_Sym {
+    pub st_name: Elf64_Word,
+    pub st_info: c_uchar,
+    pub st_other: c_uchar,
+    pub st_shndx: Elf64_Half,
+    pub st_value: Elf64_Addr,
+    pub st_size: Elf64_Xword,
+}
+
+unsafe impl FromBytes for Elf64_Sym {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Elf64_Phdr {
+    pub p_type: Elf64_Word,
+    pub p_flags: Elf64_Word,
+    pub p_offset: Elf64_Off,
+    pub p_vaddr: Elf64_Addr,
+    pub p_paddr: Elf64_Addr,
+    pub p_filesz: Elf64_Xword,
+    pub p_memsz: Elf64_Xword,
+    pub p_align: Elf64_Xword,
+}
+
+unsafe impl FromBytes for Elf64_Phdr {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Elf64_Nhdr {
+    pub n_namesz: Elf64_Word,
+    pub n_descsz: Elf64_Word,
+    pub n_type: Elf64_Word,
+}
+
+unsafe impl FromBytes for Elf64_Nhdr {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Elf64_Dyn {
+    pub d_tag: Elf64_Sxword,
+    pub d_val: Elf64_Xword,
+}
+
+unsafe impl FromBytes for Elf64_Dyn {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Elf64_Rela {
+    pub r_offset: Elf64_Addr,
+    pub r_info: Elf64_Xword,
+    pub r_addend: Elf64_Sxword,
+}
+
+unsafe impl FromBytes for Elf64_Rela {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct GnuHashHeader {
+    bucket_count: u32,
+    symbol_offset: u32,
+    bloom_size: u32,
+    bloom_shift: u32,
+}
+
+unsafe impl FromBytes for GnuHashHeader {}
+
+// resolves exports from the loaded image alone by following PT_DYNAMIC to the dynamic symbol table,
+// so it works the same for any ProcessTrait regardless of whether the file exists on this machine
+pub struct DynamicSymbols {
+    load_bias: usize,
+    image_size: usize,
+    symtab: usize,
+    strtab: usize,
+    hash: Option<usize>,
+    gnu_hash: Option<usize>
+}
+
+impl DynamicSymbols {
+    pub fn read(process: &impl ProcessTrait, module: &ProcessModule) -> Result<Self> {
+        let ehdr: Elf64_Ehdr = process.read(module.module_base)?;
+
+        if ehdr.e_ident[0..4] != ELFMAGIC {
+            return Err(Error::InvalidImage);
+        }
+
+        let mut phdrs: Vec<Elf64_Phdr> = Vec::new();
+        for i in 0..ehdr.e_phnum as usize {
+            phdrs.push(process.read(module.module_base + ehdr.e_phoff as usize + i * size_of::<Elf64_Phdr>())?);
+        }
+
+        let first_load = match phdrs.iter().filter(|phdr| phdr.p_type == PT_LOAD).map(|phdr| phdr.p_vaddr).min() {
+            Some(vaddr) => vaddr as usize & !0xFFF,
+            None => return Err(Error::InvalidImage)
+        };
+
+        // a base below the first segment means mod_find or the dump handed us something else
+        let load_bias = module.module_base.checked_sub(first_load).ok_or(Error::InvalidImage)?;
+
+        let dynamic = match phdrs.iter().find(|phdr| phdr.p_type == PT_DYNAMIC) {
+            Some(dynamic) => dynamic,
+            None => return Err(Error::NotFound)
+        };
+
+        let mut symtab = None;
+        let mut strtab = None;
+        let mut hash = None;
+        let mut gnu_hash = None;
+
+        for i in 0..dynamic.p_memsz as usize / size_of::<Elf64_Dyn>() {
+            let dyn_entry: Elf64_Dyn = process.read(load_bias + dynamic.p_vaddr as usize + i * size_of::<Elf64_Dyn>())?;
+
+            // the loader relocates most d_ptr entries in place, but not all of them and not on every libc
+            let ptr = match dyn_entry.d_val as usize {
+                ptr if ptr < module.module_base => load_bias + ptr,
+                ptr => ptr
+            };
+
+            match dyn_entry.d_tag {
+                DT_NULL => break,
+                DT_SYMTAB => symtab = Some(ptr),
+                DT_STRTAB => strtab = Some(ptr),
+                DT_HASH => hash = Some(ptr),
+                DT_GNU_HASH => gnu_hash = Some(ptr),
+                _ => ()
+            }
+        }
+
+        match (symtab, strtab) {
+            (Some(symtab), Some(strtab)) => Ok(Self { load_bias, image_size: module.module_size, symtab, strtab, hash, gnu_hash }),
+            _ => Err(Error::NotFound)
+        }
+    }
+
+    fn symbol(&self, process: &impl ProcessTrait, index: usize) -> Result<Elf64_Sym> {
+        process.read(self.symtab + index * size_of::<Elf64_Sym>())
+    }
+
+    fn symbol_name(&self, process: &impl ProcessTrait, sym: &Elf64_Sym) -> Result<String> {
+        process.read_string(self.strtab + sym.st_name as usize)
+    }
+
+    fn is_export(sym: &Elf64_Sym) -> bool {
+        sym.st_shndx != SHN_UNDEF && sym.st_value != 0
+    }
+
+    fn symbol_count(&self, process: &impl ProcessTrait) -> Result<usize> {
+        if let Some(hash) = self.hash {
+            return Ok(process.read::<u32>(hash + 4)? as usize);
+        }
+
+        let gnu_hash = match self.gnu_hash {
+            Some(gnu_hash) => gnu_hash,
+            None => return Err(Error::NotFound)
+        };
+
+        // DT_GNU_HASH has no symbol count, the last chain that any bucket points to ends the table
+        let header: GnuHashHeader = process.read(gnu_hash)?;
+        let buckets = gnu_hash + size_of::<GnuHashHeader>() + header.bloom_size as usize * 8;
+        let chains = buckets + header.bucket_count as usize * 4;
+
+        let mut last_symbol = 0;
+        for i in 0..header.bucket_count as usize {
+            last_symbol = last_symbol.max(process.read::<u32>(buckets + i * 4)? as usize);
+        }
+
+        if last_symbol < header.symbol_offset as usize {
+            return Ok(header.symbol_offset as usize);
+        }
+
+        // a chain can't run past the end of the image, without its end bit the table is broken
+        let max_symbol = header.symbol_offset as usize + self.image_size / 4;
+
+        while process.read::<u32>(chains + (last_symbol - header.symbol_offset as usize) * 4)? & 1 == 0 {
+            last_symbol += 1;
+
+            if last_symbol >= max_symbol {
+                return Err(Error::InvalidImage);
+            }
+        }
+
+        Ok(last_symbol + 1)
+    }
+
+    pub fn find(&self, process: &impl ProcessTrait, name: &str) -> Result<usize> {
+        let gnu_hash = match self.gnu_hash {
+            Some(gnu_hash) => gnu_hash,
+            None => return match self.exports(process)?.into_iter().find(|export| export.name == name) {
+                Some(export) => Ok(export.address),
+                None => Err(Error::NotFound)
+            }
+        };
+
+        let header: GnuHashHeader = process.read(gnu_hash)?;
+        if header.bucket_count == 0 {
+            return Err(Error::NotFound);
+        }
+
+        let buckets = gnu_hash + size_of::<GnuHashHeader>() + header.bloom_size as usize * 8;
+        let chains = buckets + header.bucket_count as usize * 4;
+
+        let name_hash = name.bytes().fold(5381u32, |hash, c| hash.wrapping_mul(33).wrapping_add(c as u32));
+
+        let mut index = process.read::<u32>(buckets + (name_hash % header.bucket_count) as usize * 4)? as usize;
+        if index < header.symbol_offset as usize {
+            return Err(Error::NotFound);
+        }
+
+        loop {
+            let chain_hash = process.read::<u32>(chains + (index - header.symbol_offset as usize) * 4)?;
+
+            if chain_hash | 1 == name_hash | 1 {
+                let sym = self.symbol(process, index)?;
+                if Self::is_export(&sym) && self.symbol_name(process, &sym)? == name {
+                    return Ok(self.load_bias + sym.st_value as usize);
+                }
+            }
+
+            if chain_hash & 1 != 0 {
+                break;
+            }
+
+            index += 1;
+        }
+
+        Err(Error::NotFound)
+    }
+
+    pub fn exports(&self, process: &impl ProcessTrait) -> Result<Vec<ProcessExport>> {
+        let mut exports: Vec<ProcessExport> = Vec::new();
+
+        for i in 0..self.symbol_count(process)? {
+            let sym = self.symbol(process, i)?;
+            if !Self::is_export(&sym) {
+                continue;
+            }
+
+            exports.push(ProcessExport {
+                name: self.symbol_name(process, &sym)?,
+                address: self.load_bias + sym.st_value as usize
+            });
+        }
+
+        Ok(exports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::Error, platform::tests::Memory};
+    use super::*;
+
+    const BASE: usize = 0x10000;
+
+    const SYMTAB: usize = 0x200;
+    const STRTAB: usize = 0x400;
+    const HASH: usize = 0x600;
+
+    const EXPORTS: [&str; 5] = ["alpha", "beta", "gamma", "delta", "epsilon"];
+
+    fn gnu_hash(name: &str) -> u32 {
+        name.bytes().fold(5381u32, |hash, c| hash.wrapping_mul(33).wrapping_add(c as u32))
+    }
+
+    // a shared object with one undefined import followed by EXPORTS, hashed with DT_GNU_HASH over
+    // `bucket_count` buckets or with DT_HASH when there are none
+    fn image(first_load: u64, bucket_count: Option<u32>) -> (Memory, Vec<(&'static str, usize)>) {
+        let mut data = vec![0u8; 0x1000];
+
+        let mut ehdr: Elf64_Ehdr = read(&data, 0).unwrap();
+        ehdr.e_ident[0..4].copy_from_slice(&ELFMAGIC);
+        ehdr.e_phoff = size_of::<Elf64_Ehdr>() as u64;
+        ehdr.e_phentsize = size_of::<Elf64_Phdr>() as u16;
+        ehdr.e_phnum = 2;
+        write(&mut data, 0, ehdr).unwrap();
+
+        let load = Elf64_Phdr { p_type: PT_LOAD, p_flags: 5, p_offset: 0, p_vaddr: first_load, p_paddr: first_load, p_filesz: 0x1000, p_memsz: 0x1000, p_align: 0x1000 };
+        let dynamic = Elf64_Phdr { p_type: PT_DYNAMIC, p_flags: 6, p_offset: 0x100, p_vaddr: first_load + 0x100, p_paddr: first_load + 0x100, p_filesz: 0x50, p_memsz: 0x50, p_align: 8 };
+        write(&mut data, 0x40, load).unwrap();
+        write(&mut data, 0x40 + size_of::<Elf64_Phdr>(), dynamic).unwrap();
+
+        let hash_tag = match bucket_count {
+            Some(_) => DT_GNU_HASH,
+            None => DT_HASH
+        };
+
+        let entries = [(DT_SYMTAB, SYMTAB), (DT_STRTAB, STRTAB), (hash_tag, HASH), (DT_NULL, 0)];
+        for (i, (d_tag, d_val)) in entries.into_iter().enumerate() {
+            write(&mut data, 0x100 + i * size_of::<Elf64_Dyn>(), Elf64_Dyn { d_tag, d_val: first_load + d_val as u64 }).unwrap();
+        }
+
+        // GNU hash tables need the hashed symbols grouped by bucket
+        let mut names: Vec<&str> = EXPORTS.to_vec();
+        if let Some(bucket_count) = bucket_count {
+            names.sort_by_key(|name| gnu_hash(name) % bucket_count);
+        }
+
+        let mut strings = vec![0u8];
+        let mut expected: Vec<(&str, usize)> = Vec::new();
+
+        for (i, name) in ["imported"].into_iter().chain(names.iter().copied()).enumerate() {
+            let sym = Elf64_Sym {
+                st_name: strings.len() as u32,
+                st_info: 0x12,
+                st_other: 0,
+                st_shndx: if i == 0 { SHN_UNDEF } else { 1 },
+                st_value: if i == 0 { 0 } else { first_load + 0x800 + i as u64 * 0x10 },
+                st_size: 0x10
+            };
+
+            write(&mut data, SYMTAB + (i + 1) * size_of::<Elf64_Sym>(), sym).unwrap();
+
+            strings.extend_from_slice(name.as_bytes());
+            strings.push(0);
+
+            if i != 0 {
+                expected.push((name, BASE + 0x800 + i * 0x10));
+            }
+        }
+
+        data[STRTAB..STRTAB + strings.len()].copy_from_slice(&strings);
+
+        let symbol_count = names.len() + 2;
+
+        match bucket_count {
+            Some(bucket_count) => {
+                let symbol_offset = 2;
+
+                write(&mut data, HASH, GnuHashHeader { bucket_count, symbol_offset, bloom_size: 1, bloom_shift: 6 }).unwrap();
+                write(&mut data, HASH + size_of::<GnuHashHeader>(), u64::MAX).unwrap();
+
+                let buckets = HASH + size_of::<GnuHashHeader>() + 8;
+                let chains = buckets + bucket_count as usize * 4;
+
+                for (i, name) in names.iter().enumerate() {
+                    let bucket = gnu_hash(name) % bucket_count;
+                    let index = i as u32 + symbol_offset;
+
+                    if read::<u32>(&data, buckets + bucket as usize * 4).unwrap() == 0 {
+                        write(&mut data, buckets + bucket as usize * 4, index).unwrap();
+                    }
+
+                    let last = names.get(i + 1).is_none_or(|next| gnu_hash(next) % bucket_count != bucket);
+                    write(&mut data, chains + i * 4, gnu_hash(name) & !1 | last as u32).unwrap();
+                }
+            },
+            None => {
+                write(&mut data, HASH, [1u32, symbol_count as u32]).unwrap();
+            }
+        }
+
+        (Memory::new(BASE, data), expected)
+    }
+
+    // a text and a data segment with .text, .data and .bss, and section headers at `shoff`
+    fn sectioned_image(shoff: u64) -> Vec<u8> {
+        let mut data = vec![0u8; 0x1100];
+
+        let mut ehdr: Elf64_Ehdr = read(&data, 0).unwrap();
+        ehdr.e_ident[0..4].copy_from_slice(&ELFMAGIC);
+        ehdr.e_phoff = size_of::<Elf64_Ehdr>() as u64;
+        ehdr.e_phentsize = size_of::<Elf64_Phdr>() as u16;
+        ehdr.e_phnum = 2;
+        ehdr.e_shoff = shoff;
+        ehdr.e_shentsize = size_of::<Elf64_Shdr>() as u16;
+        ehdr.e_shnum = 6;
+        ehdr.e_shstrndx = 5;
+        write(&mut data, 0, ehdr).unwrap();
+
+        let text = Elf64_Phdr { p_type: PT_LOAD, p_flags: PF_R | PF_X, p_offset: 0, p_vaddr: 0, p_paddr: 0, p_filesz: 0x1000, p_memsz: 0x1000, p_align: 0x1000 };
+        let data_segment = Elf64_Phdr { p_type: PT_LOAD, p_flags: PF_R | PF_W, p_offset: 0x1000, p_vaddr: 0x2000, p_paddr: 0x2000, p_filesz: 0x100, p_memsz: 0x200, p_align: 0x1000 };
+        write(&mut data, 0x40, text).unwrap();
+        write(&mut data, 0x40 + size_of::<Elf64_Phdr>(), data_segment).unwrap();
+
+        let names = b"\0.text\0.data\0.bss\0.comment\0.shstrtab\0";
+        data[0x700..0x700 + names.len()].copy_from_slice(names);
+
+        let shdrs = [
+            (0, 0, 0, 0, 0),
+            (1, 1, SHF_ALLOC | SHF_EXECINSTR, 0x100, 0x200),
+            (7, 1, SHF_ALLOC | 1, 0x1000, 0x80),
+            (13, SHT_NOBITS, SHF_ALLOC | 1, 0x1080, 0x100),
+            (18, 1, 0, 0x780, 0x20),
+            (27, 3, 0, 0x700, names.len() as u64)
+        ];
+
+        for (i, (sh_name, sh_type, sh_flags, sh_offset, sh_size)) in shdrs.into_iter().enumerate() {
+            let shdr = Elf64_Shdr { sh_name, sh_type, sh_flags, sh_addr: 0, sh_offset, sh_size, sh_link: 0, sh_info: 0, sh_addralign: 0, sh_entsize: 0 };
+            // headers past the end of the file are left out, as when they aren't mapped
+            let _ = write(&mut data, shoff as usize + i * size_of::<Elf64_Shdr>(), shdr);
+        }
+
+        data
+    }
+
+    fn section_layout(data: &[u8]) -> Vec<(String, usize, usize, bool)> {
+        sections(data).unwrap().into_iter().map(|section| (section.name, section.rva, section.size, section.executable)).collect()
+    }
+
+    #[test]
+    fn sections_come_from_mapped_section_headers() {
+        assert_eq!(section_layout(&sectioned_image(0x800)), [
+            (".text".to_string(), 0x100, 0x200, true),
+            (".data".to_string(), 0x2000, 0x80, false),
+            (".bss".to_string(), 0x2080, 0x100, false)
+        ]);
+    }
+
+    #[test]
+    fn sections_fall_back_to_segments_named_by_permissions() {
+        assert_eq!(section_layout(&sectioned_image(0x3000)), [
+            ("LOAD_RX".to_string(), 0, 0x1000, true),
+            ("LOAD_RW".to_string(), 0x2000, 0x200, false)
+        ]);
+    }
+
+    #[test]
+    fn find_follows_gnu_hash_chains() {
+        let (process, expected) = image(0, Some(3));
+        let symbols = DynamicSymbols::read(&process, &process.module()).unwrap();
+
+        for (name, address) in expected {
+            assert_eq!(symbols.find(&process, name).unwrap(), address, "{}", name);
+        }
+
+        assert!(matches!(symbols.find(&process, "missing"), Err(Error::NotFound)));
+        assert!(matches!(symbols.find(&process, "imported"), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn symbol_count_walks_last_gnu_chain() {
+        let (process, expected) = image(0, Some(3));
+        let symbols = DynamicSymbols::read(&process, &process.module()).unwrap();
+
+        assert_eq!(symbols.symbol_count(&process).unwrap(), EXPORTS.len() + 2);
+
+        let exports: Vec<(String, usize)> = symbols.exports(&process).unwrap().into_iter().map(|export| (export.name, export.address)).collect();
+        let expected: Vec<(String, usize)> = expected.into_iter().map(|(name, address)| (name.to_string(), address)).collect();
+
+        assert_eq!(exports, expected);
+    }
+
+    #[test]
+    fn symbol_count_reads_hash_nchain() {
+        let (process, _) = image(0, None);
+        let symbols = DynamicSymbols::read(&process, &process.module()).unwrap();
+
+        assert_eq!(symbols.symbol_count(&process).unwrap(), EXPORTS.len() + 2);
+        assert_eq!(symbols.exports(&process).unwrap().len(), EXPORTS.len());
+
+        // without DT_GNU_HASH lookups go through the exports
+        assert_eq!(symbols.find(&process, "gamma").unwrap(), BASE + 0x800 + 3 * 0x10);
+    }
+
+    #[test]
+    fn symbol_count_stops_unterminated_chains_at_the_image_end() {
+        let (mut process, _) = image(0, Some(1));
+
+        // no chain entry has its end bit, the walk would run on through whatever follows the image
+        let chains = HASH + size_of::<GnuHashHeader>() + 8 + 4;
+        process.data[chains..chains + EXPORTS.len() * 4].fill(0);
+        process.data.resize(0x10000, 0);
+
+        let module = ProcessModule { module_base: BASE, module_size: 0x1000 };
+        let symbols = DynamicSymbols::read(&process, &module).unwrap();
+
+        assert!(matches!(symbols.symbol_count(&process), Err(Error::InvalidImage)));
+    }
+
+    #[test]
+    fn relocated_values_rejects_dynamic_below_first_segment() {
+        let (process, _) = image(0x1000, Some(1));
+        let mut data = process.data;
+
+        let mut dynamic: Elf64_Phdr = read(&data, 0x40 + size_of::<Elf64_Phdr>()).unwrap();
+        dynamic.p_vaddr = 0x100;
+        write(&mut data, 0x40 + size_of::<Elf64_Phdr>(), dynamic).unwrap();
+
+        assert!(matches!(relocated_values(&data, BASE, &["alpha"]), Err(Error::InvalidImage)));
+    }
+
+    #[test]
+    fn read_rejects_base_below_first_segment() {
+        let (process, _) = image(0x400000, Some(1));
+
+        assert!(matches!(DynamicSymbols::read(&process, &process.module()), Err(Error::InvalidImage)));
+    }
+}
@@ -0,0 +1,158 @@
+pub mod elf;
+pub mod pe;
+pub mod rtti;
+
+use std::mem::{size_of, MaybeUninit};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::{error::{Error, Result}, platform::{ProcessModule, ProcessTrait}};
+
+// image handling only looks at the bytes, so dumps can be processed on any host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Elf,
+    Pe
+}
+
+impl ImageFormat {
+    pub fn detect(data: &[u8]) -> Result<Self> {
+        if data.starts_with(&elf::ELFMAGIC) {
+            Ok(Self::Elf)
+        } else if data.starts_with(&pe::IMAGE_DOS_SIGNATURE.to_le_bytes()) {
+            Ok(Self::Pe)
+        } else {
+            Err(Error::InvalidImage)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Export {
+    pub name: Option<String>,
+    pub ordinal: Option<u16>,
+    pub rva: usize,
+    pub forwarder: Option<String>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Section {
+    pub name: String,
+    pub rva: usize,
+    pub size: usize,
+    pub executable: bool
+}
+
+/// Types that any bytes are a valid value of, so they can be read straight out of memory or an image.
+///
+/// # Safety
+///
+/// Implementors have to be plain integers, arrays and `#[repr(C)]` structs of them, nothing like `bool` or enums.
+pub unsafe trait FromBytes: Copy {}
+
+macro_rules! from_bytes {
+    ($($ty:ty),*) => {
+        $(unsafe impl FromBytes for $ty {})*
+    };
+}
+
+from_bytes!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+unsafe impl<T: FromBytes, const N: usize> FromBytes for [T; N] {}
+
+// anything an image can be read from at image relative offsets, so parsers work the same on
+// a module mapped in a process and on a dump file
+pub trait ImageSource {
+    fn read_image(&self, rva: usize, data: &mut [u8]) -> Result<()>;
+
+    fn read_at<T: FromBytes>(&self, rva: usize) -> Result<T> {
+        let mut value = MaybeUninit::<T>::zeroed();
+        self.read_image(rva, unsafe {
+            std::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>())
+        })?;
+        Ok(unsafe { value.assume_init() })
+    }
+
+    fn read_string_at(&self, rva: usize) -> Result<String> {
+        let mut data: Vec<u8> = Vec::new();
+
+        let mut rva = rva;
+        while data.len() < 0x1000 {
+            let byte: u8 = self.read_at(rva)?;
+            if byte == 0 {
+                break;
+            }
+
+            data.push(byte);
+            rva += 1;
+        }
+
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+}
+
+impl ImageSource for [u8] {
+    fn read_image(&self, rva: usize, data: &mut [u8]) -> Result<()> {
+        match rva.checked_add(data.len()).and_then(|end| self.get(rva..end)) {
+            Some(bytes) => {
+                data.copy_from_slice(bytes);
+                Ok(())
+            },
+            None => Err(Error::InvalidImage)
+        }
+    }
+}
+
+pub struct ModuleImage<'a, P: ProcessTrait> {
+    pub process: &'a P,
+    pub module: &'a ProcessModule
+}
+
+impl<P: ProcessTrait> ImageSource for ModuleImage<'_, P> {
+    fn read_image(&self, rva: usize, data: &mut [u8]) -> Result<()> {
+        self.process.mem_read(self.module.module_base + rva, data)
+    }
+
+    fn read_string_at(&self, rva: usize) -> Result<String> {
+        self.process.read_string(self.module.module_base + rva)
+    }
+}
+
+pub fn fix_image(allocation_base: usize, data: &mut [u8]) -> Result<()> {
+    match ImageFormat::detect(data)? {
+        ImageFormat::Elf => elf::fix_image(data),
+        ImageFormat::Pe => pe::fix_image(allocation_base, data)
+    }
+}
+
+pub fn sections(data: &[u8]) -> Result<Vec<Section>> {
+    match ImageFormat::detect(data)? {
+        ImageFormat::Elf => elf::sections(data),
+        ImageFormat::Pe => pe::sections(data)
+    }
+}
+
+pub fn get_timestamp(data: &[u8]) -> Result<DateTime<Utc>> {
+    match ImageFormat::detect(data)? {
+        ImageFormat::Elf => elf::get_timestamp(data),
+        ImageFormat::Pe => pe::get_timestamp(data)
+    }
+}
+
+pub fn read<T: FromBytes>(data: &[u8], offset: usize) -> Result<T> {
+    match offset.checked_add(size_of::<T>()) {
+        Some(end) if end <= data.len() => {
+            Ok(unsafe { std::ptr::read_unaligned(data.as_ptr().add(offset) as *const T) })
+        },
+        _ => Err(Error::InvalidImage)
+    }
+}
+
+pub fn write<T: Copy>(data: &mut [u8], offset: usize, value: T) -> Result<()> {
+    match offset.checked_add(size_of::<T>()) {
+        Some(end) if end <= data.len() => {
+            unsafe { std::ptr::write_unaligned(data.as_mut_ptr().add(offset) as *mut T, value) };
+            Ok(())
+        },
+        _ => Err(Error::InvalidImage)
+    }
+}
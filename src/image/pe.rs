@@ -0,0 +1,286 @@
+use std::{collections::HashMap, mem::size_of};
+use chrono::{DateTime, Utc};
+use pelite::image::{
+    IMAGE_DATA_DIRECTORY, IMAGE_DIRECTORY_ENTRY_EXCEPTION, IMAGE_DIRECTORY_ENTRY_EXPORT, IMAGE_EXPORT_DIRECTORY,
+    IMAGE_FILE_HEADER, IMAGE_NT_HEADERS64, IMAGE_NT_HEADERS_SIGNATURE, IMAGE_SCN_MEM_EXECUTE, IMAGE_SECTION_HEADER,
+    RUNTIME_FUNCTION
+};
+use crate::error::{Error, Result};
+use super::{read, write, Export, FromBytes, ImageSource, Section};
+
+pub use pelite::image::{IMAGE_DOS_HEADER, IMAGE_DOS_SIGNATURE};
+
+// pelite's headers are plain integer structs
+unsafe impl FromBytes for IMAGE_DOS_HEADER {}
+unsafe impl FromBytes for IMAGE_NT_HEADERS64 {}
+unsafe impl FromBytes for IMAGE_DATA_DIRECTORY {}
+unsafe impl FromBytes for IMAGE_SECTION_HEADER {}
+unsafe impl FromBytes for IMAGE_EXPORT_DIRECTORY {}
+unsafe impl FromBytes for RUNTIME_FUNCTION {}
+
+pub fn nt_headers_offset(image: &(impl ImageSource + ?Sized)) -> Result<usize> {
+    let dos_header: IMAGE_DOS_HEADER = image.read_at(0)?;
+    if dos_header.e_magic != IMAGE_DOS_SIGNATURE {
+        return Err(Error::InvalidImage);
+    }
+
+    let nt_headers_offset = dos_header.e_lfanew as usize;
+
+    let nt_headers: IMAGE_NT_HEADERS64 = image.read_at(nt_headers_offset)?;
+    if nt_headers.Signature != IMAGE_NT_HEADERS_SIGNATURE {
+        return Err(Error::InvalidImage);
+    }
+
+    Ok(nt_headers_offset)
+}
+
+pub fn section_headers_offset(nt_headers_offset: usize, nt_headers: &IMAGE_NT_HEADERS64) -> usize {
+    nt_headers_offset
+        + size_of::<u32>()
+        + size_of::<IMAGE_FILE_HEADER>()
+        + nt_headers.FileHeader.SizeOfOptionalHeader as usize
+}
+
+pub fn image_base(data: &[u8]) -> Result<usize> {
+    let nt_headers: IMAGE_NT_HEADERS64 = read(data, nt_headers_offset(data)?)?;
+    Ok(nt_headers.OptionalHeader.ImageBase as usize)
+}
+
+pub fn fix_image(allocation_base: usize, data: &mut [u8]) -> Result<()> {
+    let nt_headers_offset = nt_headers_offset(&*data)?;
+
+    let mut nt_headers: IMAGE_NT_HEADERS64 = read(data, nt_headers_offset)?;
+    nt_headers.OptionalHeader.ImageBase = allocation_base as u64;
+    write(data, nt_headers_offset, nt_headers)?;
+
+    let section_headers = section_headers_offset(nt_headers_offset, &nt_headers);
+
+    for i in 0..nt_headers.FileHeader.NumberOfSections as usize {
+        let offset = section_headers + i * size_of::<IMAGE_SECTION_HEADER>();
+
+        let mut section: IMAGE_SECTION_HEADER = read(data, offset)?;
+        section.PointerToRawData = section.VirtualAddress;
+        section.SizeOfRawData = section.VirtualSize;
+        write(data, offset, section)?;
+    }
+
+    Ok(())
+}
+
+pub fn sections(image: &(impl ImageSource + ?Sized)) -> Result<Vec<Section>> {
+    let nt_headers_offset = nt_headers_offset(image)?;
+
+    let nt_headers: IMAGE_NT_HEADERS64 = image.read_at(nt_headers_offset)?;
+
+    let section_headers = section_headers_offset(nt_headers_offset, &nt_headers);
+
+    let mut sections: Vec<Section> = Vec::new();
+
+    for i in 0..nt_headers.FileHeader.NumberOfSections as usize {
+        let section: IMAGE_SECTION_HEADER = image.read_at(section_headers + i * size_of::<IMAGE_SECTION_HEADER>())?;
+
+        sections.push(Section {
+            name: String::from_utf8_lossy(&section.Name).trim_end_matches('\0').to_string(),
+            rva: section.VirtualAddress as usize,
+            size: section.VirtualSize as usize,
+            executable: section.Characteristics & IMAGE_SCN_MEM_EXECUTE != 0
+        });
+    }
+
+    Ok(sections)
+}
+
+// the range of the function containing `rva` according to the exception directory, which x64
+// images carry for every function that isn't a leaf
+pub fn function_at(data: &[u8], rva: usize) -> Result<Option<(usize, usize)>> {
+    let nt_headers_offset = nt_headers_offset(data)?;
+
+    let nt_headers: IMAGE_NT_HEADERS64 = read(data, nt_headers_offset)?;
+    if nt_headers.OptionalHeader.NumberOfRvaAndSizes as usize <= IMAGE_DIRECTORY_ENTRY_EXCEPTION {
+        return Ok(None);
+    }
+
+    let exception_data_directory: IMAGE_DATA_DIRECTORY = read(data, nt_headers_offset
+        + size_of::<IMAGE_NT_HEADERS64>()
+        + IMAGE_DIRECTORY_ENTRY_EXCEPTION * size_of::<IMAGE_DATA_DIRECTORY>())?;
+
+    let count = exception_data_directory.Size as usize / size_of::<RUNTIME_FUNCTION>();
+
+    let entry = |i: usize| read::<RUNTIME_FUNCTION>(data, exception_data_directory.VirtualAddress as usize + i * size_of::<RUNTIME_FUNCTION>());
+
+    // entries are sorted by address
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let middle = (low + high) / 2;
+        let function = entry(middle)?;
+
+        if rva < function.BeginAddress as usize {
+            high = middle;
+        } else if rva >= function.EndAddress as usize {
+            low = middle + 1;
+        } else {
+            return Ok(Some((function.BeginAddress as usize, function.EndAddress as usize)));
+        }
+    }
+
+    Ok(None)
+}
+
+pub fn get_timestamp(data: &[u8]) -> Result<DateTime<Utc>> {
+    let nt_headers: IMAGE_NT_HEADERS64 = read(data, nt_headers_offset(data)?)?;
+
+    match DateTime::from_timestamp(nt_headers.FileHeader.TimeDateStamp.into(), 0) {
+        Some(timestamp) => Ok(timestamp),
+        None => Err(Error::InvalidImage)
+    }
+}
+
+pub fn exports(image: &(impl ImageSource + ?Sized)) -> Result<Vec<Export>> {
+    let nt_headers_offset = nt_headers_offset(image)?;
+
+    let nt_headers: IMAGE_NT_HEADERS64 = image.read_at(nt_headers_offset)?;
+    if nt_headers.OptionalHeader.NumberOfRvaAndSizes as usize <= IMAGE_DIRECTORY_ENTRY_EXPORT {
+        return Ok(Vec::new());
+    }
+
+    let export_data_directory: IMAGE_DATA_DIRECTORY = image.read_at(nt_headers_offset
+        + size_of::<IMAGE_NT_HEADERS64>()
+        + IMAGE_DIRECTORY_ENTRY_EXPORT * size_of::<IMAGE_DATA_DIRECTORY>())?;
+
+    if export_data_directory.VirtualAddress == 0 {
+        return Ok(Vec::new());
+    }
+
+    // forwarded exports point at a "module.function" string inside the export directory instead of code
+    let export_directory_range = export_data_directory.VirtualAddress as usize
+        ..export_data_directory.VirtualAddress as usize + export_data_directory.Size as usize;
+
+    let export_directory: IMAGE_EXPORT_DIRECTORY = image.read_at(export_data_directory.VirtualAddress as usize)?;
+
+    // several names can share one ordinal, each of them is its own export
+    let mut names: HashMap<usize, Vec<String>> = HashMap::new();
+
+    for i in 0..export_directory.NumberOfNames as usize {
+        let name_rva: u32 = image.read_at(export_directory.AddressOfNames as usize + i * 4)?;
+        let index: u16 = image.read_at(export_directory.AddressOfNameOrdinals as usize + i * 2)?;
+
+        names.entry(index as usize).or_default().push(image.read_string_at(name_rva as usize)?);
+    }
+
+    let mut exports: Vec<Export> = Vec::new();
+
+    for i in 0..export_directory.NumberOfFunctions as usize {
+        let rva: u32 = image.read_at(export_directory.AddressOfFunctions as usize + i * 4)?;
+        if rva == 0 {
+            continue;
+        }
+
+        let forwarder = match export_directory_range.contains(&(rva as usize)) {
+            true => Some(image.read_string_at(rva as usize)?),
+            false => None
+        };
+
+        let names = match names.remove(&i) {
+            Some(names) => names.into_iter().map(Some).collect(),
+            None => vec![None]
+        };
+
+        for name in names {
+            exports.push(Export {
+                name,
+                ordinal: Some((export_directory.Base as usize + i) as u16),
+                rva: rva as usize,
+                forwarder: forwarder.clone()
+            });
+        }
+    }
+
+    Ok(exports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT_DIRECTORY: usize = 0x200;
+
+    // ordinal 10 has a name and an alias, 11 is unused, 12 is exported by ordinal only and 13 forwards to another module
+    fn image() -> Vec<u8> {
+        let mut data = vec![0u8; 0x1000];
+
+        let mut dos_header: IMAGE_DOS_HEADER = read(&data, 0).unwrap();
+        dos_header.e_magic = IMAGE_DOS_SIGNATURE;
+        dos_header.e_lfanew = 0x40;
+        write(&mut data, 0, dos_header).unwrap();
+
+        let mut nt_headers: IMAGE_NT_HEADERS64 = read(&data, 0x40).unwrap();
+        nt_headers.Signature = IMAGE_NT_HEADERS_SIGNATURE;
+        nt_headers.OptionalHeader.NumberOfRvaAndSizes = 16;
+        write(&mut data, 0x40, nt_headers).unwrap();
+
+        let export_data_directory = IMAGE_DATA_DIRECTORY { VirtualAddress: EXPORT_DIRECTORY as u32, Size: 0x200 };
+        write(&mut data, 0x40 + size_of::<IMAGE_NT_HEADERS64>(), export_data_directory).unwrap();
+
+        let functions = 0x300;
+        let names = 0x320;
+        let ordinals = 0x340;
+        let strings = 0x360;
+
+        let mut export_directory: IMAGE_EXPORT_DIRECTORY = read(&data, EXPORT_DIRECTORY).unwrap();
+        export_directory.Base = 10;
+        export_directory.NumberOfFunctions = 4;
+        export_directory.NumberOfNames = 3;
+        export_directory.AddressOfFunctions = functions;
+        export_directory.AddressOfNames = names;
+        export_directory.AddressOfNameOrdinals = ordinals;
+        write(&mut data, EXPORT_DIRECTORY, export_directory).unwrap();
+
+        write(&mut data, functions as usize, [0x1000u32, 0, 0x1100, strings + 0x20]).unwrap();
+
+        // names are sorted, so the alias doesn't have to follow the name it shares an ordinal with
+        let entries = [("Create", 0u16), ("CreateAlias", 0), ("Forwarded", 3)];
+        for (i, (name, index)) in entries.into_iter().enumerate() {
+            let name_rva = strings as usize + i * 0x10 + 0x40;
+            data[name_rva..name_rva + name.len()].copy_from_slice(name.as_bytes());
+
+            write(&mut data, names as usize + i * 4, name_rva as u32).unwrap();
+            write(&mut data, ordinals as usize + i * 2, index).unwrap();
+        }
+
+        let forwarder = b"other.Function";
+        data[strings as usize + 0x20..strings as usize + 0x20 + forwarder.len()].copy_from_slice(forwarder);
+
+        data
+    }
+
+    #[test]
+    fn exports_every_name_of_an_ordinal() {
+        let exports = exports(&image()[..]).unwrap();
+
+        let names: Vec<Option<&str>> = exports.iter().map(|export| export.name.as_deref()).collect();
+        let ordinals: Vec<Option<u16>> = exports.iter().map(|export| export.ordinal).collect();
+        let rvas: Vec<usize> = exports.iter().map(|export| export.rva).collect();
+        let forwarders: Vec<Option<&str>> = exports.iter().map(|export| export.forwarder.as_deref()).collect();
+
+        assert_eq!(names, [Some("Create"), Some("CreateAlias"), None, Some("Forwarded")]);
+        assert_eq!(ordinals, [Some(10), Some(10), Some(12), Some(13)]);
+        assert_eq!(rvas, [0x1000, 0x1000, 0x1100, 0x380]);
+        assert_eq!(forwarders, [None, None, None, Some("other.Function")]);
+    }
+
+    #[test]
+    fn exports_without_export_directory() {
+        let mut data = image();
+        write(&mut data, 0x40 + size_of::<IMAGE_NT_HEADERS64>(), IMAGE_DATA_DIRECTORY { VirtualAddress: 0, Size: 0 }).unwrap();
+
+        assert!(exports(&data[..]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn exports_rejects_missing_signature() {
+        let mut data = image();
+        write(&mut data, 0, 0u16).unwrap();
+
+        assert!(matches!(exports(&data[..]), Err(Error::InvalidImage)));
+    }
+}
@@ -0,0 +1,268 @@
+use std::{collections::HashMap, mem::size_of};
+use serde::{Deserialize, Serialize};
+use crate::error::Result;
+use super::{elf, read, sections, FromBytes, ImageFormat, Section};
+
+const MAX_VTABLE_FUNCTIONS: usize = 0x400;
+const MAX_NAME_LENGTH: usize = 0x400;
+
+// the largest subobject offset a secondary vtable is accepted with
+const MAX_OFFSET_TO_TOP: i64 = 0x100000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VTable {
+    pub class_name: String,
+    // rva of the first function pointer
+    pub rva: usize,
+    // offset of the subobject the vtable is for, 0 for the primary vtable
+    pub offset: usize,
+    pub functions: usize
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct RttiCompleteObjectLocator {
+    signature: u32,                 // 0x0000
+    offset: u32,                    // 0x0004
+    cd_offset: u32,                 // 0x0008
+    type_descriptor: u32,           // 0x000C
+    class_descriptor: u32,          // 0x0010
+    this: u32                       // 0x0014
+}
+
+unsafe impl FromBytes for RttiCompleteObjectLocator {}
+
+// x64 locators are image relative and point back at themselves
+const COL_SIGNATURE_X64: u32 = 1;
+
+// offset of the decorated name in a TypeDescriptor, after its vtable pointer and spare pointer
+const TYPE_DESCRIPTOR_NAME: usize = 0x10;
+
+// the runtime's vtables of the typeinfo classes for classes without bases, with one and with several
+const CLASS_TYPE_INFO_VTABLES: [&str; 3] = [
+    "_ZTVN10__cxxabiv117__class_type_infoE",
+    "_ZTVN10__cxxabiv120__si_class_type_infoE",
+    "_ZTVN10__cxxabiv121__vmi_class_type_infoE"
+];
+
+// finds vtables through the RTTI the compiler emitted: complete object locators in PE images and
+// Itanium typeinfo in ELF images. `data` is the image as it was mapped at `base`, so pointers in it are absolute
+pub fn vtables(data: &[u8], base: usize) -> Result<Vec<VTable>> {
+    let executable: Vec<Section> = sections(data)?.into_iter()
+        .filter(|section| section.executable)
+        .collect();
+
+    let mut vtables = match ImageFormat::detect(data)? {
+        ImageFormat::Pe => msvc_vtables(data, base),
+        ImageFormat::Elf => itanium_vtables(data, base)
+    };
+
+    for vtable in &mut vtables {
+        vtable.functions = (0..MAX_VTABLE_FUNCTIONS)
+            .take_while(|i| match pointer(data, base, vtable.rva + i * size_of::<u64>()) {
+                Some(function) => executable.iter().any(|section| (section.rva..section.rva + section.size).contains(&function)),
+                None => false
+            })
+            .count();
+    }
+
+    vtables.retain(|vtable| vtable.functions != 0);
+
+    Ok(vtables)
+}
+
+fn msvc_vtables(data: &[u8], base: usize) -> Vec<VTable> {
+    let mut locators: HashMap<usize, (String, usize)> = HashMap::new();
+
+    for rva in (0..data.len().saturating_sub(size_of::<RttiCompleteObjectLocator>())).step_by(4) {
+        let locator: RttiCompleteObjectLocator = match read(data, rva) {
+            Ok(locator) => locator,
+            Err(_) => break
+        };
+
+        if locator.signature != COL_SIGNATURE_X64 || locator.this as usize != rva {
+            continue;
+        }
+
+        let class_name = string(data, locator.type_descriptor as usize + TYPE_DESCRIPTOR_NAME)
+            .and_then(msvc_class_name);
+
+        if let Some(class_name) = class_name {
+            locators.insert(rva, (class_name, locator.offset as usize));
+        }
+    }
+
+    let mut vtables: Vec<VTable> = Vec::new();
+
+    if locators.is_empty() {
+        return vtables;
+    }
+
+    // the locator pointer sits right before the first function pointer
+    for rva in (0..data.len().saturating_sub(size_of::<u64>())).step_by(size_of::<u64>()) {
+        if let Some((class_name, offset)) = pointer(data, base, rva).and_then(|locator| locators.get(&locator)) {
+            vtables.push(VTable {
+                class_name: class_name.clone(),
+                rva: rva + size_of::<u64>(),
+                offset: *offset,
+                functions: 0
+            });
+        }
+    }
+
+    vtables
+}
+
+fn itanium_vtables(data: &[u8], base: usize) -> Vec<VTable> {
+    // class typeinfo objects start with a pointer into one of the runtime's typeinfo vtables, which the
+    // loader put there through a relocation, followed by a pointer to the mangled name
+    let class_type_info_vtables = match elf::relocated_values(data, base, &CLASS_TYPE_INFO_VTABLES) {
+        Ok(vtables) if !vtables.is_empty() => vtables,
+        _ => return Vec::new()
+    };
+
+    let mut typeinfos: HashMap<usize, String> = HashMap::new();
+
+    for rva in (0..data.len().saturating_sub(2 * size_of::<u64>())).step_by(size_of::<u64>()) {
+        if !class_type_info_vtables.contains(&(read::<u64>(data, rva).unwrap_or(0) as usize)) {
+            continue;
+        }
+
+        let class_name = pointer(data, base, rva + size_of::<u64>())
+            .and_then(|name| string(data, name))
+            .and_then(itanium_class_name);
+
+        if let Some(class_name) = class_name {
+            typeinfos.insert(rva, class_name);
+        }
+    }
+
+    let mut vtables: Vec<VTable> = Vec::new();
+
+    // the typeinfo pointer sits right before the first function pointer and the offset to the top of the
+    // object before that, which is 0 for the primary vtable and the negated, pointer aligned subobject offset otherwise
+    for rva in (0..data.len().saturating_sub(2 * size_of::<u64>())).step_by(size_of::<u64>()) {
+        let offset_to_top: i64 = match read(data, rva) {
+            Ok(offset_to_top) if offset_to_top <= 0 && offset_to_top > -MAX_OFFSET_TO_TOP && offset_to_top % 8 == 0 => offset_to_top,
+            _ => continue
+        };
+
+        if let Some(class_name) = pointer(data, base, rva + size_of::<u64>()).and_then(|typeinfo| typeinfos.get(&typeinfo)) {
+            vtables.push(VTable {
+                class_name: class_name.clone(),
+                rva: rva + 2 * size_of::<u64>(),
+                offset: offset_to_top.unsigned_abs() as usize,
+                functions: 0
+            });
+        }
+    }
+
+    vtables
+}
+
+// reads an absolute pointer and returns it as an rva if it points into the image
+fn pointer(data: &[u8], base: usize, offset: usize) -> Option<usize> {
+    let address = read::<u64>(data, offset).ok()? as usize;
+    Some(address.wrapping_sub(base)).filter(|rva| *rva < data.len())
+}
+
+fn string(data: &[u8], offset: usize) -> Option<&str> {
+    let bytes = data.get(offset..)?;
+    let bytes = &bytes[..bytes.len().min(MAX_NAME_LENGTH)];
+    let length = bytes.iter().position(|byte| *byte == 0)?;
+    std::str::from_utf8(&bytes[..length]).ok()
+}
+
+// ".?AVInner@Outer@@" -> "Outer::Inner", templates and other special names are kept decorated
+fn msvc_class_name(name: &str) -> Option<String> {
+    let name = name.strip_prefix(".?AV").or_else(|| name.strip_prefix(".?AU"))?;
+    let name = name.strip_suffix("@@")?;
+
+    if name.is_empty() || !name.is_ascii() {
+        return None;
+    }
+
+    if name.contains(['?', '$']) {
+        return Some(name.to_string());
+    }
+
+    Some(name.split('@').rev().collect::<Vec<&str>>().join("::"))
+}
+
+// "5Outer" -> "Outer", "N5Outer5InnerE" -> "Outer::Inner" and "St9exception" -> "std::exception",
+// anything fancier isn't considered a class name
+fn itanium_class_name(name: &str) -> Option<String> {
+    let (rest, nested) = match name.strip_prefix('N') {
+        Some(rest) => (rest.strip_suffix('E')?, true),
+        None => (name, false)
+    };
+
+    let mut parts: Vec<&str> = Vec::new();
+
+    let mut rest = match rest.strip_prefix("St") {
+        Some(rest) => {
+            parts.push("std");
+            rest
+        },
+        None => rest
+    };
+
+    let namespaces = parts.len();
+
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let length: usize = rest[..digits].parse().ok()?;
+
+        // a length near usize::MAX would overflow, no real name gets there
+        let end = digits.checked_add(length)?;
+
+        let part = rest.get(digits..end)?;
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return None;
+        }
+
+        parts.push(part);
+        rest = &rest[end..];
+    }
+
+    match (parts.len() - namespaces, nested) {
+        (0, _) => None,
+        (1, _) | (_, true) => Some(parts.join("::")),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn msvc_class_names() {
+        assert_eq!(msvc_class_name(".?AVC_BaseEntity@@").as_deref(), Some("C_BaseEntity"));
+        assert_eq!(msvc_class_name(".?AUInner@Outer@game@@").as_deref(), Some("game::Outer::Inner"));
+        assert_eq!(msvc_class_name(".?AV?$CUtlVector@H@@@@").as_deref(), Some("?$CUtlVector@H@@"));
+    }
+
+    #[test]
+    fn msvc_class_names_reject_other_types() {
+        for name in [".?AW4Team@@", ".?AH", ".?AV@@", ".?AVC_BaseEntity", "C_BaseEntity"] {
+            assert_eq!(msvc_class_name(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn itanium_class_names() {
+        assert_eq!(itanium_class_name("12C_BaseEntity").as_deref(), Some("C_BaseEntity"));
+        assert_eq!(itanium_class_name("N4game5Outer5InnerE").as_deref(), Some("game::Outer::Inner"));
+        assert_eq!(itanium_class_name("St9exception").as_deref(), Some("std::exception"));
+        assert_eq!(itanium_class_name("NSt8ios_base7failureE").as_deref(), Some("std::ios_base::failure"));
+    }
+
+    #[test]
+    fn itanium_class_names_reject_other_types() {
+        // templates, unnested qualified names, truncated or overflowing lengths and builtin types
+        for name in ["N4game6VectorIiEE", "4game5Outer", "12C_Base", "18446744073709551615C", "i", "St", "N4gameE5Outer", ""] {
+            assert_eq!(itanium_class_name(name), None, "{}", name);
+        }
+    }
+}
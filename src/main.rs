@@ -1,18 +1,98 @@
 mod error;
 mod config;
+mod diff;
 mod game;
+mod image;
+mod output;
+mod pattern;
 mod platform;
+mod signature;
 
 use config::Config;
 use error::Error;
+use pattern::Pattern;
 use platform::ProcessTrait;
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
-    #[arg(short, long)]
-    process: String
+    #[arg(short, long, required_unless_present_any = ["dumps", "core", "minidump"])]
+    process: Option<String>,
+
+    /// Run against the module dumps of a manifest or output directory instead of a live process
+    #[arg(long, conflicts_with_all = ["process", "core", "minidump"])]
+    dumps: Option<PathBuf>,
+
+    /// Run against an ELF core file instead of a live process
+    #[arg(long, conflicts_with_all = ["process", "minidump"])]
+    core: Option<PathBuf>,
+
+    /// Run against a full memory minidump of the Windows game instead of a live process
+    #[arg(long, conflicts_with = "process")]
+    minidump: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Fix up and store a module image that was dumped elsewhere
+    Import {
+        path: PathBuf,
+        /// Module name to store the image under, defaults to the file name
+        #[arg(long)]
+        name: Option<String>,
+        /// Address the image was mapped at, defaults to the PE image base
+        #[arg(long, value_parser = parse_address)]
+        base: Option<usize>
+    },
+    /// Compare two runs, each given as an output directory or a manifest
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        /// Directory diff.json and diff.md are written to
+        #[arg(long, default_value = "output/diff")]
+        output: PathBuf
+    },
+    /// Compare the sections of two dumps of the same module
+    DiffImage {
+        old: PathBuf,
+        new: PathBuf,
+        /// Directory image_diff.json and image_diff.md are written to
+        #[arg(long, default_value = "output/diff")]
+        output: PathBuf
+    },
+    /// Generate the shortest unique signature for an address in the .text of a dumped module
+    Signature {
+        /// Module name as recorded in output/manifest.json, or the path of a dump
+        module: String,
+        #[arg(value_parser = parse_address)]
+        rva: usize
+    },
+    /// Find where an address of an old module dump went in a new one and generate a signature for it there
+    Migrate {
+        /// Dump the address belongs to, by module name or path
+        old: String,
+        #[arg(value_parser = parse_address)]
+        rva: usize,
+        /// Dump of the new build, by module name or path
+        new: String
+    },
+    /// Scan a module of the attached process for a byte pattern, e.g. "48 8B 05 ? ? ? ? 48 85 C0"
+    Scan {
+        module: String,
+        pattern: Pattern
+    }
+}
+
+fn parse_address(value: &str) -> Result<usize, std::num::ParseIntError> {
+    match value.strip_prefix("0x") {
+        Some(value) => usize::from_str_radix(value, 16),
+        None => value.parse()
+    }
 }
 
 fn main() -> Result<(), Error> {
@@ -27,19 +107,202 @@ fn main() -> Result<(), Error> {
         }
     };
 
-    let mut process = match platform::Process::find_process_by_name(&args.process) {
+    if let Some(Command::Import { path, name, base }) = &args.command {
+        match game::modules::import(path, name.as_deref(), *base) {
+            Ok(module) => println!("imported module: {} at 0x{:X}", module.name, module.base),
+            Err(err) => println!("failed to import module: {}, error: {}", path.display(), err)
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Diff { old, new, output }) = &args.command {
+        diff::run(old, new, output);
+        return Ok(());
+    }
+
+    if let Some(Command::Signature { module, rva }) = &args.command {
+        signature::run(module, *rva);
+        return Ok(());
+    }
+
+    if let Some(Command::Migrate { old, rva, new }) = &args.command {
+        signature::migrate::run(old, *rva, new);
+        return Ok(());
+    }
+
+    if let Some(Command::DiffImage { old, new, output }) = &args.command {
+        diff::image::run(old, new, output);
+        return Ok(());
+    }
+
+    if let Some(path) = &args.dumps {
+        let process = match platform::file::Process::from_manifest(path) {
+            Ok(process) => process,
+            Err(err) => {
+                println!("couldn't map module dumps: {}, error: {}", path.display(), err);
+                return Ok(());
+            }
+        };
+
+        // the dumps are already on disk, so they're recorded as they are instead of dumped again
+        let modules = match output::model::Model::load(path) {
+            Ok(model) => model.modules,
+            Err(_) => Vec::new()
+        };
+
+        // the manifest names the modules for the platform the dumps came from
+        let names: Vec<&str> = modules.iter().map(|module| module.name.as_str()).collect();
+        let config = config.with_default_modules(&names);
+
+        run(&process, &config, args.command.as_ref(), Some(modules));
+        return Ok(());
+    }
+
+    if let Some(path) = &args.core {
+        let process = match platform::coredump::Process::open(path) {
+            Ok(process) => process,
+            Err(err) => {
+                println!("couldn't open core file: {}, error: {}", path.display(), err);
+                return Ok(());
+            }
+        };
+
+        let config = config.with_default_modules(&platform::LINUX_MODULES);
+
+        run(&process, &config, args.command.as_ref(), None);
+        return Ok(());
+    }
+
+    if let Some(path) = &args.minidump {
+        let process = match platform::minidump::Process::open(path) {
+            Ok(process) => process,
+            Err(err) => {
+                println!("couldn't open minidump: {}, error: {}", path.display(), err);
+                return Ok(());
+            }
+        };
+
+        let config = config.with_default_modules(&platform::WINDOWS_MODULES);
+
+        run(&process, &config, args.command.as_ref(), None);
+        return Ok(());
+    }
+
+    let process_name = match args.process {
+        Some(process_name) => process_name,
+        None => Args::command()
+            .error(ErrorKind::MissingRequiredArgument, "the --process argument is required for this command")
+            .exit()
+    };
+
+    let mut process = match platform::Process::find_process_by_name(&process_name) {
         Ok(process) => process,
         Err(err) => {
-            println!("couldn't attach to process: {}, error: {}", args.process, err);
+            println!("couldn't attach to process: {}, error: {}", process_name, err);
             return Ok(());
         }
     };
 
     process.attach()?;
 
-    println!("attached to {}", args.process);
+    println!("attached to {}", process_name);
 
-    game::modules::dump(&mut process, &config);
+    run(&process, &config, args.command.as_ref(), None);
 
     Ok(())
 }
+
+// everything that only needs memory and modules, from a live process or from dumps. `modules` are the
+// dumps already on disk, without them the configured modules are dumped unless a command was given
+fn run(process: &impl ProcessTrait, config: &Config, command: Option<&Command>, modules: Option<Vec<game::modules::DumpedModule>>) {
+    if let Some(Command::Scan { module, pattern }) = command {
+        scan(process, module, pattern);
+        return;
+    }
+
+    let mut images = game::modules::ModuleImages::new();
+
+    let modules = match (modules, command, config.dump_modules) {
+        (Some(modules), ..) => modules,
+        (None, None, true) => game::modules::dump(process, config, &mut images),
+        // nothing is dumped this run, the last manifest's modules and imports are kept instead of wiped
+        _ => match output::model::Model::load(Path::new("output/manifest.json")) {
+            Ok(model) => model.modules,
+            Err(_) => Vec::new()
+        }
+    };
+
+    let scopes = match config.dump_schema {
+        true => game::schema::dump(process),
+        false => Vec::new()
+    };
+
+    let interfaces = match config.dump_interfaces {
+        true => game::interfaces::dump(process, config),
+        false => Vec::new()
+    };
+
+    let vtables = match config.dump_vtables {
+        true => game::vtables::dump(process, config, &mut images),
+        false => Vec::new()
+    };
+
+    let offsets = game::offsets::dump(process, config, &mut images);
+
+    let model = output::model::Model::new(modules, scopes, interfaces, vtables, offsets);
+
+    match model.save(Path::new("output/manifest.json")) {
+        Ok(_) => println!("wrote manifest: output/manifest.json"),
+        Err(err) => println!("failed to write manifest, error: {}", err)
+    }
+
+    if config.generate_cpp {
+        output::cpp::write(&model.schema, config.cpp_structs);
+    }
+
+    if config.generate_rust {
+        output::rust::write(&model.schema, &model.offsets);
+    }
+
+    if config.generate_csharp {
+        output::csharp::write(&model.schema, &model.offsets);
+    }
+
+    if config.generate_python {
+        output::python::write(&model.schema, &model.offsets);
+    }
+
+    if config.generate_ida {
+        output::ida::write(&model);
+    }
+
+    if config.generate_binja {
+        output::binja::write(&model);
+    }
+
+    if config.generate_ghidra {
+        output::ghidra::write(&model);
+    }
+
+    output::templates::render(&model, &config.templates);
+}
+
+fn scan(process: &impl ProcessTrait, mod_name: &str, pattern: &Pattern) {
+    let module = match process.mod_find(mod_name) {
+        Ok(module) => module,
+        Err(err) => {
+            println!("failed to find module: {}, error: {}", mod_name, err);
+            return;
+        }
+    };
+
+    match pattern.scan_module(process, &module) {
+        Ok(matches) => {
+            for address in &matches {
+                println!("{}+0x{:X} (0x{:X})", mod_name, address - module.module_base, address);
+            }
+            println!("{} matches for {}", matches.len(), pattern);
+        },
+        Err(err) => println!("failed to scan module: {}, error: {}", mod_name, err)
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt::Write;
+use crate::game::modules::{module_directory, DumpedModule};
+use super::{model::Model, module_symbols, write_c_declarations, write_output, write_python_symbols, ClassIndex, ModuleTypes};
+
+pub fn write(model: &Model) {
+    let index = ClassIndex::new(&model.schema);
+
+    for module in &model.modules {
+        let path = module_directory(&module.name).join("binja.py");
+
+        match generate(model, module, &index).map_err(Into::into).and_then(|script| write_output(&path, &script)) {
+            Ok(_) => println!("generated binja script: {}", path.display()),
+            Err(err) => println!("failed to generate binja script: {}, error: {}", path.display(), err)
+        }
+    }
+}
+
+fn generate(model: &Model, module: &DumpedModule, index: &ClassIndex) -> Result<String, std::fmt::Error> {
+    let types = ModuleTypes::new(&model.schema, index, &module.name);
+    let symbols = module_symbols(model, module);
+
+    let mut declarations = String::new();
+    write_c_declarations(&mut declarations, &types, index)?;
+
+    let mut out = String::new();
+
+    writeln!(out, "# Generated by cs2-dumper for {}", module.name)?;
+    writeln!(out, "#")?;
+    writeln!(out, "# Open {} in Binary Ninja and run this script with File > Run Script.", module.file)?;
+    writeln!(out)?;
+    writeln!(out, "from binaryninja import Symbol, SymbolType, Type")?;
+    writeln!(out)?;
+    writeln!(out, "DECLARATIONS = r\"\"\"")?;
+    out.push_str(&declarations);
+    writeln!(out, "\"\"\"")?;
+
+    write_python_symbols(&mut out, &symbols)?;
+    out.push_str(SCRIPT);
+
+    Ok(out)
+}
+
+const SCRIPT: &str = r#"
+
+def main(bv):
+    # the first segment of a dumped image maps its headers, so the view starts at the image base
+    base = bv.start
+
+    try:
+        types = bv.parse_types_from_string(DECLARATIONS).types
+    except SyntaxError as err:
+        print("failed to parse the schema types: %s" % err)
+        types = {}
+
+    for name, type in types.items():
+        bv.define_user_type(name, type)
+
+    for rva, name in FUNCTIONS:
+        bv.create_user_function(base + rva)
+        bv.define_user_symbol(Symbol(SymbolType.FunctionSymbol, base + rva, name))
+
+    for rva, name in DATA:
+        bv.define_user_symbol(Symbol(SymbolType.DataSymbol, base + rva, name))
+
+    pointer = Type.pointer(bv.arch, Type.void())
+
+    for rva, name, functions in VTABLES:
+        bv.define_user_data_var(base + rva, Type.array(pointer, max(functions, 1)))
+        bv.define_user_symbol(Symbol(SymbolType.DataSymbol, base + rva, name))
+
+    bv.update_analysis()
+
+    print("applied %d types and %d names" % (len(types), len(FUNCTIONS) + len(DATA) + len(VTABLES)))
+
+
+main(bv)
+"#;
@@ -0,0 +1,168 @@
+use std::{collections::BTreeSet, fmt::{Result, Write}, path::Path};
+use crate::game::schema::SchemaScope;
+use super::{identifier, scope_name, write_class_comment, write_output, ClassIndex, Layout, Member};
+
+pub fn write(scopes: &[SchemaScope], structs: bool) {
+    let index = ClassIndex::new(scopes);
+
+    for scope in scopes {
+        let path = Path::new("output/cpp").join(scope_name(scope)).with_extension("hpp");
+
+        match generate(scope, &index, structs).map_err(Into::into).and_then(|header| write_output(&path, &header)) {
+            Ok(_) => println!("generated c++ header: {}", path.display()),
+            Err(err) => println!("failed to generate c++ header: {}, error: {}", path.display(), err)
+        }
+    }
+}
+
+fn generate(scope: &SchemaScope, index: &ClassIndex, structs: bool) -> std::result::Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(out, "// Generated by cs2-dumper from schema scope {}", scope.name)?;
+    writeln!(out)?;
+    writeln!(out, "#pragma once")?;
+    writeln!(out)?;
+    writeln!(out, "#include <cstddef>")?;
+    writeln!(out, "#include <cstdint>")?;
+
+    if structs {
+        let includes: BTreeSet<String> = scope.classes.iter()
+            .filter_map(|class| index.base_class(class))
+            .map(|(base_scope, _)| scope_name(base_scope))
+            .filter(|name| *name != scope_name(scope))
+            .collect();
+
+        if !includes.is_empty() {
+            writeln!(out)?;
+        }
+
+        for include in includes {
+            writeln!(out, "#include \"{}.hpp\"", include)?;
+        }
+    }
+
+    writeln!(out)?;
+    write_offsets(&mut out, scope)?;
+
+    if structs {
+        writeln!(out)?;
+        write_structs(&mut out, scope, index)?;
+    }
+
+    Ok(out)
+}
+
+fn write_offsets(out: &mut String, scope: &SchemaScope) -> Result {
+    writeln!(out, "namespace cs2_dumper {{")?;
+    writeln!(out, "    namespace schemas {{")?;
+    writeln!(out, "        namespace {} {{", scope_name(scope))?;
+
+    for (i, class) in scope.classes.iter().enumerate() {
+        if i != 0 {
+            writeln!(out)?;
+        }
+
+        write_class_comment(out, class, "            ")?;
+
+        writeln!(out, "            namespace {} {{", identifier(&class.name))?;
+
+        let mut fields: Vec<_> = class.fields.iter().collect();
+        fields.sort_by_key(|field| field.offset);
+
+        for field in fields {
+            writeln!(out, "                constexpr std::ptrdiff_t {} = 0x{:X}; // {}", identifier(&field.name), field.offset, field.type_name)?;
+        }
+
+        writeln!(out, "            }}")?;
+    }
+
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}")
+}
+
+fn write_structs(out: &mut String, scope: &SchemaScope, index: &ClassIndex) -> Result {
+    // schema offsets are exact, so members are packed and all padding is spelled out
+    writeln!(out, "#pragma pack(push, 1)")?;
+    writeln!(out)?;
+    writeln!(out, "namespace cs2_dumper {{")?;
+    writeln!(out, "    namespace types {{")?;
+    writeln!(out, "        namespace {} {{", scope_name(scope))?;
+
+    for (i, class) in index.sorted_by_inheritance(scope).into_iter().enumerate() {
+        if i != 0 {
+            writeln!(out)?;
+        }
+
+        let name = identifier(&class.name);
+
+        let layout = match Layout::new(class, index) {
+            Some(layout) => layout,
+            None => {
+                writeln!(out, "            // {}: layout unknown, a base class is missing from the dump", class.name)?;
+                continue;
+            }
+        };
+
+        write_class_comment(out, class, "            ")?;
+
+        match layout.base.and_then(|base| index.get(&base.name)) {
+            Some((base_scope, base)) => writeln!(out, "            struct {} : public ::cs2_dumper::types::{}::{} {{",
+                name, scope_name(base_scope), identifier(&base.name))?,
+            None => writeln!(out, "            struct {} {{", name)?
+        }
+
+        for member in &layout.members {
+            match member {
+                Member::Padding { offset, size } => {
+                    writeln!(out, "                uint8_t pad_0x{:04X}[0x{:X}];", offset, size)?;
+                },
+                Member::Field { field, primitive: true, .. } => {
+                    writeln!(out, "                {} {}; // 0x{:X}", cpp_type(&field.type_name), identifier(&field.name), field.offset)?;
+                },
+                Member::Field { field, size, .. } => {
+                    writeln!(out, "                uint8_t {}[0x{:X}]; // 0x{:X} {}", identifier(&field.name), size, field.offset, field.type_name)?;
+                }
+            }
+        }
+
+        for field in &layout.overlapping {
+            writeln!(out, "                // {} at 0x{:X} overlaps a previous field ({})", field.name, field.offset, field.type_name)?;
+        }
+
+        writeln!(out, "            }};")?;
+
+        writeln!(out, "            static_assert(sizeof({}) == 0x{:X});", name, class.size)?;
+
+        for member in &layout.members {
+            if let Member::Field { field, .. } = member {
+                writeln!(out, "            static_assert(offsetof({}, {}) == 0x{:X});", name, identifier(&field.name), field.offset)?;
+            }
+        }
+    }
+
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+    writeln!(out, "#pragma pack(pop)")
+}
+
+
+fn cpp_type(type_name: &str) -> &'static str {
+    match type_name {
+        "bool" => "bool",
+        "char" => "char",
+        "int8" => "int8_t",
+        "uint8" => "uint8_t",
+        "int16" => "int16_t",
+        "uint16" => "uint16_t",
+        "int32" => "int32_t",
+        "uint32" => "uint32_t",
+        "int64" => "int64_t",
+        "uint64" => "uint64_t",
+        "float32" => "float",
+        "float64" => "double",
+        _ => "void*"
+    }
+}
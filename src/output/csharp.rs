@@ -0,0 +1,221 @@
+use std::{fmt::{Result, Write}, path::Path};
+use crate::game::{offsets::Offsets, schema::{SchemaClass, SchemaEnum, SchemaScope}};
+use super::{identifier, module_name, scope_name, write_class_comment, write_output, ClassIndex, EnumRepr, Layout, Member};
+
+const KEYWORDS: [&str; 77] = [
+    "abstract", "as", "base", "bool", "break", "byte", "case", "catch", "char", "checked", "class", "const",
+    "continue", "decimal", "default", "delegate", "do", "double", "else", "enum", "event", "explicit", "extern",
+    "false", "finally", "fixed", "float", "for", "foreach", "goto", "if", "implicit", "in", "int", "interface",
+    "internal", "is", "lock", "long", "namespace", "new", "null", "object", "operator", "out", "override",
+    "params", "private", "protected", "public", "readonly", "ref", "return", "sbyte", "sealed", "short", "sizeof",
+    "stackalloc", "static", "string", "struct", "switch", "this", "throw", "true", "try", "typeof", "uint",
+    "ulong", "unchecked", "unsafe", "ushort", "using", "virtual", "void", "volatile", "while"
+];
+
+pub fn write(scopes: &[SchemaScope], offsets: &Offsets) {
+    let index = ClassIndex::new(scopes);
+
+    let mut files: Vec<(String, std::result::Result<String, std::fmt::Error>)> = vec![
+        ("Offsets".to_string(), generate_offsets(offsets))
+    ];
+
+    for scope in scopes {
+        files.push((scope_name(scope), generate_scope(scope, &index)));
+    }
+
+    for (name, source) in files {
+        let path = Path::new("output/csharp").join(name).with_extension("cs");
+
+        match source.map_err(Into::into).and_then(|source| write_output(&path, &source)) {
+            Ok(_) => println!("generated c# source: {}", path.display()),
+            Err(err) => println!("failed to generate c# source: {}, error: {}", path.display(), err)
+        }
+    }
+}
+
+fn generate_offsets(offsets: &Offsets) -> std::result::Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(out, "// Generated by cs2-dumper from offsets.json")?;
+    writeln!(out)?;
+    writeln!(out, "namespace CS2Dumper {{")?;
+    writeln!(out, "    public static class Offsets {{")?;
+
+    for (i, (module, offsets)) in offsets.iter().enumerate() {
+        if i != 0 {
+            writeln!(out)?;
+        }
+
+        writeln!(out, "        // {}", module)?;
+        writeln!(out, "        public static class {} {{", csharp_identifier(&module_name(module)))?;
+
+        for (name, offset) in offsets {
+            writeln!(out, "            public const nint {} = 0x{:X};", csharp_identifier(name), offset)?;
+        }
+
+        writeln!(out, "        }}")?;
+    }
+
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}")?;
+
+    Ok(out)
+}
+
+fn generate_scope(scope: &SchemaScope, index: &ClassIndex) -> std::result::Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(out, "// Generated by cs2-dumper from schema scope {}", scope.name)?;
+    writeln!(out)?;
+    writeln!(out, "using System;")?;
+    writeln!(out, "using System.Runtime.InteropServices;")?;
+    writeln!(out)?;
+    writeln!(out, "namespace CS2Dumper.Schemas.{} {{", scope_name(scope))?;
+
+    write_offsets(&mut out, scope)?;
+
+    for schema_enum in &scope.enums {
+        writeln!(out)?;
+        write_enum(&mut out, schema_enum)?;
+    }
+
+    for class in index.sorted_by_inheritance(scope) {
+        writeln!(out)?;
+
+        match Layout::new(class, index) {
+            Some(layout) => write_struct(&mut out, class, &layout, index)?,
+            None => writeln!(out, "    // {}: layout unknown, a base class is missing from the dump", class.name)?
+        }
+    }
+
+    writeln!(out, "}}")?;
+
+    Ok(out)
+}
+
+fn write_offsets(out: &mut String, scope: &SchemaScope) -> Result {
+    writeln!(out, "    public static class Offsets {{")?;
+
+    for (i, class) in scope.classes.iter().enumerate() {
+        if i != 0 {
+            writeln!(out)?;
+        }
+
+        write_class_comment(out, class, "        ")?;
+
+        writeln!(out, "        public static class {} {{", csharp_identifier(&class.name))?;
+
+        let mut fields: Vec<_> = class.fields.iter().collect();
+        fields.sort_by_key(|field| field.offset);
+
+        for field in fields {
+            writeln!(out, "            public const nint {} = 0x{:X}; // {}", member_identifier(&field.name, &class.name), field.offset, field.type_name)?;
+        }
+
+        writeln!(out, "        }}")?;
+    }
+
+    writeln!(out, "    }}")
+}
+
+fn write_enum(out: &mut String, schema_enum: &SchemaEnum) -> Result {
+    let repr = EnumRepr::new(schema_enum);
+
+    let underlying = match (repr.bits, repr.signed) {
+        (8, false) => "byte",
+        (8, true) => "sbyte",
+        (16, false) => "ushort",
+        (16, true) => "short",
+        (32, false) => "uint",
+        (32, true) => "int",
+        (_, false) => "ulong",
+        (_, true) => "long"
+    };
+
+    writeln!(out, "    // Size: 0x{:X}", schema_enum.size)?;
+
+    if schema_enum.is_flags {
+        writeln!(out, "    [Flags]")?;
+    }
+
+    writeln!(out, "    public enum {} : {} {{", csharp_identifier(&schema_enum.name), underlying)?;
+
+    for enumerator in &schema_enum.enumerators {
+        let value = repr.value(enumerator.value);
+
+        let value = match schema_enum.is_flags && value >= 0 {
+            true => format!("0x{:X}", value),
+            false => value.to_string()
+        };
+
+        writeln!(out, "        {} = {},", member_identifier(&enumerator.name, &schema_enum.name), value)?;
+    }
+
+    writeln!(out, "    }}")
+}
+
+// explicit layout places every field itself, so padding is implied by the offsets and the struct size
+fn write_struct(out: &mut String, class: &SchemaClass, layout: &Layout, index: &ClassIndex) -> Result {
+    write_class_comment(out, class, "    ")?;
+
+    writeln!(out, "    [StructLayout(LayoutKind.Explicit, Size = 0x{:X})]", class.size)?;
+    writeln!(out, "    public unsafe struct {} {{", csharp_identifier(&class.name))?;
+
+    if let Some((base_scope, base)) = layout.base.and_then(|base| index.get(&base.name)) {
+        writeln!(out, "        [FieldOffset(0x0)] public global::CS2Dumper.Schemas.{}.{} Base;", scope_name(base_scope), csharp_identifier(&base.name))?;
+    }
+
+    for member in &layout.members {
+        match member {
+            Member::Padding { .. } => (),
+            Member::Field { field, primitive: true, .. } => {
+                writeln!(out, "        [FieldOffset(0x{:X})] public {} {};", field.offset, csharp_type(&field.type_name), member_identifier(&field.name, &class.name))?;
+            },
+            Member::Field { field, size, .. } => {
+                writeln!(out, "        [FieldOffset(0x{:X})] public fixed byte {}[0x{:X}]; // {}", field.offset, member_identifier(&field.name, &class.name), size, field.type_name)?;
+            }
+        }
+    }
+
+    for field in &layout.overlapping {
+        writeln!(out, "        // {} at 0x{:X} overlaps a previous field ({})", field.name, field.offset, field.type_name)?;
+    }
+
+    writeln!(out, "    }}")
+}
+
+fn csharp_type(type_name: &str) -> &'static str {
+    match type_name {
+        "bool" => "bool",
+        "char" => "sbyte",
+        "int8" => "sbyte",
+        "uint8" => "byte",
+        "int16" => "short",
+        "uint16" => "ushort",
+        "int32" => "int",
+        "uint32" => "uint",
+        "int64" => "long",
+        "uint64" => "ulong",
+        "float32" => "float",
+        "float64" => "double",
+        // pointers into the game, always 64 bit regardless of the host
+        _ => "ulong"
+    }
+}
+
+// members can't share the name of the type they're declared in
+fn member_identifier(name: &str, parent: &str) -> String {
+    match csharp_identifier(name) {
+        name if name == csharp_identifier(parent) => format!("{}_", name),
+        name => name
+    }
+}
+
+fn csharp_identifier(name: &str) -> String {
+    let name = identifier(name);
+
+    match KEYWORDS.contains(&name.as_str()) {
+        true => format!("@{}", name),
+        false => name
+    }
+}
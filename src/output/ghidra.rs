@@ -0,0 +1,186 @@
+use std::fmt::Write;
+use crate::game::modules::{module_directory, DumpedModule};
+use super::{identifier, model::Model, module_symbols, python_string, write_output, write_python_symbols, ClassIndex, EnumRepr, Layout, Member, ModuleTypes};
+
+pub fn write(model: &Model) {
+    let index = ClassIndex::new(&model.schema);
+
+    for module in &model.modules {
+        let path = module_directory(&module.name).join("ghidra.py");
+
+        match generate(model, module, &index).map_err(Into::into).and_then(|script| write_output(&path, &script)) {
+            Ok(_) => println!("generated ghidra script: {}", path.display()),
+            Err(err) => println!("failed to generate ghidra script: {}, error: {}", path.display(), err)
+        }
+    }
+}
+
+// ghidra's C parser doesn't take everything the other disassemblers do, so the types are
+// built through its api instead, from the same layouts
+fn generate(model: &Model, module: &DumpedModule, index: &ClassIndex) -> Result<String, std::fmt::Error> {
+    let types = ModuleTypes::new(&model.schema, index, &module.name);
+    let symbols = module_symbols(model, module);
+
+    let mut out = String::new();
+
+    writeln!(out, "# Generated by cs2-dumper for {}", module.name)?;
+    writeln!(out, "#")?;
+    writeln!(out, "# Import {} and run this script from the Script Manager, or headless with", module.file)?;
+    writeln!(out, "# analyzeHeadless <project> <name> -import {} -postScript ghidra.py", module.file)?;
+    writeln!(out, "# @category cs2-dumper")?;
+    writeln!(out)?;
+    writeln!(out, "from ghidra.program.model.data import (")?;
+    writeln!(out, "    ArrayDataType, BooleanDataType, ByteDataType, CategoryPath, CharDataType, DataTypeConflictHandler,")?;
+    writeln!(out, "    DoubleDataType, EnumDataType, FloatDataType, IntegerDataType, LongLongDataType, PointerDataType,")?;
+    writeln!(out, "    ShortDataType, SignedByteDataType, StructureDataType, UnsignedIntegerDataType, UnsignedLongLongDataType,")?;
+    writeln!(out, "    UnsignedShortDataType")?;
+    writeln!(out, ")")?;
+    writeln!(out, "from ghidra.program.model.symbol import SourceType")?;
+    writeln!(out)?;
+    writeln!(out, "# name, size, enumerators")?;
+    writeln!(out, "ENUMS = [")?;
+
+    for schema_enum in &types.enums {
+        let repr = EnumRepr::new(schema_enum);
+
+        writeln!(out, "    ({}, {}, [", python_string(&identifier(&schema_enum.name)), schema_enum.size)?;
+
+        for enumerator in &schema_enum.enumerators {
+            // ghidra keeps enumerators as java longs
+            writeln!(out, "        ({}, {}),", python_string(&identifier(&enumerator.name)), repr.value(enumerator.value) as i64)?;
+        }
+
+        writeln!(out, "    ]),")?;
+    }
+
+    writeln!(out, "]")?;
+    writeln!(out)?;
+    writeln!(out, "# name, size, base class, fields as (offset, size, primitive, schema type, name)")?;
+    writeln!(out, "STRUCTS = [")?;
+
+    for class in &types.classes {
+        let layout = match Layout::new(class, index) {
+            Some(layout) => layout,
+            None => continue
+        };
+
+        let base = match layout.base {
+            Some(base) => python_string(&identifier(&base.name)),
+            None => "None".to_string()
+        };
+
+        writeln!(out, "    ({}, 0x{:X}, {}, [", python_string(&identifier(&class.name)), class.size, base)?;
+
+        for member in &layout.members {
+            if let Member::Field { field, size, primitive } = member {
+                let primitive = match primitive {
+                    true => "True",
+                    false => "False"
+                };
+
+                writeln!(out, "        (0x{:X}, 0x{:X}, {}, {}, {}),", field.offset, size, primitive, python_string(&field.type_name), python_string(&identifier(&field.name)))?;
+            }
+        }
+
+        writeln!(out, "    ]),")?;
+    }
+
+    writeln!(out, "]")?;
+
+    write_python_symbols(&mut out, &symbols)?;
+    out.push_str(SCRIPT);
+
+    Ok(out)
+}
+
+const SCRIPT: &str = r#"
+
+CATEGORY = CategoryPath("/cs2-dumper")
+
+PRIMITIVES = {
+    "bool": BooleanDataType.dataType,
+    "char": CharDataType.dataType,
+    "int8": SignedByteDataType.dataType,
+    "uint8": ByteDataType.dataType,
+    "int16": ShortDataType.dataType,
+    "uint16": UnsignedShortDataType.dataType,
+    "int32": IntegerDataType.dataType,
+    "uint32": UnsignedIntegerDataType.dataType,
+    "int64": LongLongDataType.dataType,
+    "uint64": UnsignedLongLongDataType.dataType,
+    "float32": FloatDataType.dataType,
+    "float64": DoubleDataType.dataType,
+}
+
+
+def set_name(address, name):
+    try:
+        createLabel(address, name, True, SourceType.USER_DEFINED)
+    except Exception as err:
+        print("failed to name %s as %s: %s" % (address, name, err))
+
+
+def define_types(manager):
+    types = {}
+
+    for name, size, enumerators in ENUMS:
+        enum = EnumDataType(CATEGORY, name, size)
+        for enumerator, value in enumerators:
+            enum.add(enumerator, value)
+        types[name] = manager.addDataType(enum, DataTypeConflictHandler.REPLACE_HANDLER)
+
+    # base classes come first, so they're defined by the time a derived class embeds them
+    for name, size, base, fields in STRUCTS:
+        struct = StructureDataType(CATEGORY, name, size)
+
+        if base is not None:
+            struct.replaceAtOffset(0, types[base], types[base].getLength(), "base", None)
+
+        for offset, field_size, primitive, type_name, field in fields:
+            if primitive:
+                data_type = PRIMITIVES.get(type_name, PointerDataType.dataType)
+            else:
+                data_type = ArrayDataType(ByteDataType.dataType, field_size, 1)
+
+            try:
+                struct.replaceAtOffset(offset, data_type, field_size, field, type_name)
+            except Exception as err:
+                print("failed to add %s.%s: %s" % (name, field, err))
+
+        types[name] = manager.addDataType(struct, DataTypeConflictHandler.REPLACE_HANDLER)
+
+    return types
+
+
+def main():
+    # a dumped PE keeps the base it was mapped at and ghidra picks its own for an ELF,
+    # either way the rvas below are relative to the image base
+    base = currentProgram.getImageBase()
+
+    types = define_types(currentProgram.getDataTypeManager())
+
+    for rva, name in FUNCTIONS:
+        address = base.add(rva)
+        if getFunctionAt(address) is None:
+            disassemble(address)
+            createFunction(address, None)
+        set_name(address, name)
+
+    for rva, name in DATA:
+        set_name(base.add(rva), name)
+
+    for rva, name, functions in VTABLES:
+        address = base.add(rva)
+        length = max(functions, 1)
+        try:
+            clearListing(address, address.add(8 * length - 1))
+            createData(address, ArrayDataType(PointerDataType.dataType, length, 8))
+        except Exception as err:
+            print("failed to mark the vtable at %s: %s" % (address, err))
+        set_name(address, name)
+
+    print("applied %d types and %d names" % (len(types), len(FUNCTIONS) + len(DATA) + len(VTABLES)))
+
+
+main()
+"#;
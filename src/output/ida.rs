@@ -0,0 +1,92 @@
+use std::fmt::Write;
+use crate::game::modules::{module_directory, DumpedModule};
+use super::{identifier, model::Model, module_symbols, python_string, write_c_declarations, write_output, write_python_symbols, ClassIndex, Layout, ModuleTypes};
+
+pub fn write(model: &Model) {
+    let index = ClassIndex::new(&model.schema);
+
+    for module in &model.modules {
+        let path = module_directory(&module.name).join("ida.py");
+
+        match generate(model, module, &index).map_err(Into::into).and_then(|script| write_output(&path, &script)) {
+            Ok(_) => println!("generated ida script: {}", path.display()),
+            Err(err) => println!("failed to generate ida script: {}, error: {}", path.display(), err)
+        }
+    }
+}
+
+fn generate(model: &Model, module: &DumpedModule, index: &ClassIndex) -> Result<String, std::fmt::Error> {
+    let types = ModuleTypes::new(&model.schema, index, &module.name);
+    let symbols = module_symbols(model, module);
+
+    let mut declarations = String::new();
+    write_c_declarations(&mut declarations, &types, index)?;
+
+    let mut out = String::new();
+
+    writeln!(out, "# Generated by cs2-dumper for {}", module.name)?;
+    writeln!(out, "#")?;
+    writeln!(out, "# Open {} in IDA and run this script with File > Script file.", module.file)?;
+    writeln!(out)?;
+    writeln!(out, "import ida_bytes")?;
+    writeln!(out, "import ida_funcs")?;
+    writeln!(out, "import ida_name")?;
+    writeln!(out, "import idaapi")?;
+    writeln!(out, "import idc")?;
+    writeln!(out)?;
+    // a dumped PE is rebased to where it was mapped and a dumped ELF loads at 0,
+    // either way the image base is what the rvas below are relative to
+    writeln!(out, "BASE = idaapi.get_imagebase()")?;
+    writeln!(out)?;
+    writeln!(out, "DECLARATIONS = r\"\"\"")?;
+    out.push_str(&declarations);
+    writeln!(out, "\"\"\"")?;
+    writeln!(out)?;
+    writeln!(out, "STRUCTS = [")?;
+
+    for class in types.classes.iter().filter(|class| Layout::new(class, index).is_some()) {
+        writeln!(out, "    {},", python_string(&identifier(&class.name)))?;
+    }
+
+    writeln!(out, "]")?;
+
+    write_python_symbols(&mut out, &symbols)?;
+    out.push_str(SCRIPT);
+
+    Ok(out)
+}
+
+const SCRIPT: &str = r#"
+
+def set_name(ea, name):
+    if not ida_name.set_name(ea, name, ida_name.SN_NOWARN | ida_name.SN_NOCHECK | ida_name.SN_FORCE):
+        print("failed to name 0x%X as %s" % (ea, name))
+
+
+def main():
+    errors = idc.parse_decls(DECLARATIONS, 0)
+    if errors:
+        print("%d errors while parsing the schema types" % errors)
+
+    # before IDA 9 local types have to be imported to show up as structures
+    import_type = getattr(idc, "import_type", None)
+    if import_type is not None:
+        for name in STRUCTS:
+            import_type(-1, name)
+
+    for rva, name in FUNCTIONS:
+        ida_funcs.add_func(BASE + rva)
+        set_name(BASE + rva, name)
+
+    for rva, name in DATA:
+        set_name(BASE + rva, name)
+
+    for rva, name, functions in VTABLES:
+        ida_bytes.create_data(BASE + rva, ida_bytes.FF_QWORD, 8 * max(functions, 1), idaapi.BADADDR)
+        set_name(BASE + rva, name)
+
+    print("applied %d types and %d names" % (len(STRUCTS), len(FUNCTIONS) + len(DATA) + len(VTABLES)))
+
+
+main()
+"#;
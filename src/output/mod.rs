@@ -0,0 +1,635 @@
+pub mod binja;
+pub mod cpp;
+pub mod csharp;
+pub mod ghidra;
+pub mod ida;
+pub mod model;
+pub mod python;
+pub mod rust;
+pub mod templates;
+
+use crate::{game::{modules::DumpedModule, offsets::Offset, schema::{SchemaClass, SchemaEnum, SchemaField, SchemaScope}}, image::rtti::VTable};
+use std::{collections::{HashMap, HashSet}, fmt, fs::{create_dir_all, File}, io::Write, path::Path};
+use crate::error::Result;
+use model::Model;
+
+// where every dumped class lives, so base classes can be resolved across scopes
+pub struct ClassIndex<'a> {
+    classes: HashMap<&'a str, (&'a SchemaScope, &'a SchemaClass)>
+}
+
+impl<'a> ClassIndex<'a> {
+    pub fn new(scopes: &'a [SchemaScope]) -> Self {
+        let mut classes = HashMap::new();
+
+        for scope in scopes {
+            for class in &scope.classes {
+                classes.entry(class.name.as_str()).or_insert((scope, class));
+            }
+        }
+
+        Self { classes }
+    }
+
+    pub fn get(&self, name: &str) -> Option<(&'a SchemaScope, &'a SchemaClass)> {
+        self.classes.get(name).copied()
+    }
+
+    pub fn base_class(&self, class: &SchemaClass) -> Option<(&'a SchemaScope, &'a SchemaClass)> {
+        class.base_classes.first().and_then(|name| self.get(name))
+    }
+
+    // whether every class in the inheritance chain was dumped with a usable size
+    pub fn is_complete(&self, class: &SchemaClass) -> bool {
+        let mut class = class;
+
+        for _ in 0..64 {
+            if class.size <= 0 {
+                return false;
+            }
+
+            match class.base_classes.first() {
+                Some(name) => match self.get(name) {
+                    Some((_, base_class)) => class = base_class,
+                    None => return false
+                },
+                None => return true
+            }
+        }
+
+        false
+    }
+
+    // length of the inheritance chain, bounded like is_complete
+    pub fn depth(&self, class: &SchemaClass) -> usize {
+        let mut depth = 0;
+        let mut class = class;
+
+        while let Some((_, base_class)) = self.base_class(class) {
+            if depth >= 64 {
+                break;
+            }
+
+            depth += 1;
+            class = base_class;
+        }
+
+        depth
+    }
+
+    // classes of a scope ordered so that base classes from the same scope come first
+    pub fn sorted_by_inheritance(&self, scope: &'a SchemaScope) -> Vec<&'a SchemaClass> {
+        let mut sorted: Vec<&SchemaClass> = Vec::new();
+
+        for class in &scope.classes {
+            self.visit(scope, class, &mut sorted, 0);
+        }
+
+        sorted
+    }
+
+    fn visit(&self, scope: &'a SchemaScope, class: &'a SchemaClass, sorted: &mut Vec<&'a SchemaClass>, depth: usize) {
+        if sorted.iter().any(|sorted| std::ptr::eq(*sorted, class)) {
+            return;
+        }
+
+        if let Some((base_scope, base_class)) = self.base_class(class) {
+            if std::ptr::eq(base_scope, scope) && depth < scope.classes.len() {
+                self.visit(scope, base_class, sorted, depth + 1);
+            }
+        }
+
+        sorted.push(class);
+    }
+}
+
+pub enum Member<'a> {
+    Field {
+        field: &'a SchemaField,
+        size: usize,
+        // whether the field is emitted with its own primitive type rather than as raw bytes
+        primitive: bool
+    },
+    Padding {
+        offset: usize,
+        size: usize
+    }
+}
+
+pub struct Layout<'a> {
+    pub base: Option<&'a SchemaClass>,
+    pub members: Vec<Member<'a>>,
+    // fields sharing storage with a previous field, e.g. bitfields
+    pub overlapping: Vec<&'a SchemaField>
+}
+
+impl<'a> Layout<'a> {
+    // None when the layout can't be fully known, i.e. a base class wasn't dumped
+    pub fn new(class: &'a SchemaClass, index: &ClassIndex<'a>) -> Option<Self> {
+        if !index.is_complete(class) {
+            return None;
+        }
+
+        let base = match class.base_classes.first() {
+            Some(name) => Some(index.get(name)?.1),
+            None => None
+        };
+
+        let size = class.size.max(0) as usize;
+        let start = base.map(|base| base.size.max(0) as usize).unwrap_or(0);
+
+        if start > size {
+            return None;
+        }
+
+        let mut fields: Vec<&SchemaField> = class.fields.iter().collect();
+        fields.sort_by_key(|field| field.offset);
+
+        let mut members: Vec<Member> = Vec::new();
+        let mut overlapping: Vec<&SchemaField> = Vec::new();
+
+        let mut cursor = start;
+
+        for (i, field) in fields.iter().enumerate() {
+            let offset = field.offset.max(0) as usize;
+
+            if offset < cursor || offset >= size {
+                overlapping.push(field);
+                continue;
+            }
+
+            if offset > cursor {
+                members.push(Member::Padding { offset: cursor, size: offset - cursor });
+            }
+
+            let next = fields[i + 1..].iter()
+                .map(|field| field.offset.max(0) as usize)
+                .find(|next| *next > offset)
+                .unwrap_or(size)
+                .min(size);
+
+            let (field_size, primitive) = match primitive_size(&field.type_name) {
+                Some(primitive_size) if primitive_size <= next - offset => (primitive_size, true),
+                _ => (next - offset, false)
+            };
+
+            members.push(Member::Field { field, size: field_size, primitive });
+
+            cursor = offset + field_size;
+        }
+
+        if cursor < size {
+            members.push(Member::Padding { offset: cursor, size: size - cursor });
+        }
+
+        Some(Self { base, members, overlapping })
+    }
+}
+
+// integer type backing an enum, signed as soon as any enumerator is negative
+pub struct EnumRepr {
+    pub bits: u32,
+    pub signed: bool
+}
+
+impl EnumRepr {
+    pub fn new(schema_enum: &SchemaEnum) -> Self {
+        Self {
+            bits: match schema_enum.size {
+                1 => 8,
+                2 => 16,
+                4 => 32,
+                _ => 64
+            },
+            signed: schema_enum.enumerators.iter().any(|enumerator| enumerator.value < 0)
+        }
+    }
+
+    // truncated to the enum size, then read back with the signedness of the repr
+    pub fn value(&self, value: i64) -> i128 {
+        let shift = 64 - self.bits;
+        let value = (value as u64) << shift;
+
+        match self.signed {
+            true => ((value as i64) >> shift) as i128,
+            false => (value >> shift) as i128
+        }
+    }
+}
+
+// schema types that belong to a module: the scope named after it, the base classes its classes derive from
+// and the enums their fields use, ordered so that base classes come first
+pub struct ModuleTypes<'a> {
+    pub classes: Vec<&'a SchemaClass>,
+    pub enums: Vec<&'a SchemaEnum>
+}
+
+impl<'a> ModuleTypes<'a> {
+    pub fn new(scopes: &'a [SchemaScope], index: &ClassIndex<'a>, module: &str) -> Self {
+        let mut classes: Vec<&SchemaClass> = Vec::new();
+        let mut class_names: HashSet<&str> = HashSet::new();
+
+        for scope in scopes.iter().filter(|scope| scope.name.eq_ignore_ascii_case(module)) {
+            for class in &scope.classes {
+                let mut class = Some(class);
+
+                while let Some(current) = class {
+                    if !class_names.insert(&current.name) {
+                        break;
+                    }
+
+                    classes.push(current);
+                    class = index.base_class(current).map(|(_, base_class)| base_class);
+                }
+            }
+        }
+
+        classes.sort_by_key(|class| index.depth(class));
+
+        let all_enums: HashMap<&str, &SchemaEnum> = scopes.iter().rev()
+            .flat_map(|scope| &scope.enums)
+            .map(|schema_enum| (schema_enum.name.as_str(), schema_enum))
+            .collect();
+
+        let mut enums: Vec<&SchemaEnum> = Vec::new();
+        let mut enum_names: HashSet<&str> = HashSet::new();
+
+        let own_enums = scopes.iter()
+            .filter(|scope| scope.name.eq_ignore_ascii_case(module))
+            .flat_map(|scope| &scope.enums);
+
+        let used_enums = classes.iter()
+            .flat_map(|class| &class.fields)
+            .filter_map(|field| all_enums.get(field.type_name.as_str()).copied());
+
+        for schema_enum in own_enums.chain(used_enums) {
+            if enum_names.insert(&schema_enum.name) {
+                enums.push(schema_enum);
+            }
+        }
+
+        Self { classes, enums }
+    }
+}
+
+// plain C for the type parsers of disassemblers, laid out like the padded c++ structs
+pub fn write_c_declarations(out: &mut String, types: &ModuleTypes, index: &ClassIndex) -> fmt::Result {
+    use fmt::Write;
+
+    writeln!(out, "#pragma pack(push, 1)")?;
+
+    // enumerators share one namespace in C
+    let mut enumerators: HashSet<String> = HashSet::new();
+
+    // C enums can't pick their underlying type, so the enum is a typedef of its integer type and the
+    // enumerators are constants, in an anonymous enum when they fit in an int and as defines otherwise
+    for schema_enum in &types.enums {
+        let repr = EnumRepr::new(schema_enum);
+
+        let constants: Vec<(String, i128)> = schema_enum.enumerators.iter()
+            .map(|enumerator| {
+                let name = identifier(&enumerator.name);
+
+                match enumerators.insert(name.clone()) {
+                    true => (name, repr.value(enumerator.value)),
+                    false => (format!("{}__{}", identifier(&schema_enum.name), name), repr.value(enumerator.value))
+                }
+            })
+            .collect();
+
+        writeln!(out)?;
+        writeln!(out, "typedef {} {};", c_integer(&repr), identifier(&schema_enum.name))?;
+
+        if constants.is_empty() {
+            continue;
+        }
+
+        if constants.iter().all(|(_, value)| i32::try_from(*value).is_ok()) {
+            writeln!(out, "enum {{")?;
+
+            for (name, value) in &constants {
+                writeln!(out, "    {} = {},", name, value)?;
+            }
+
+            writeln!(out, "}};")?;
+        } else {
+            let suffix = match repr.signed {
+                true => "LL",
+                false => "ULL"
+            };
+
+            for (name, value) in &constants {
+                writeln!(out, "#define {} ({}{})", name, value, suffix)?;
+            }
+        }
+    }
+
+    for class in &types.classes {
+        writeln!(out)?;
+
+        let layout = match Layout::new(class, index) {
+            Some(layout) => layout,
+            None => {
+                writeln!(out, "// {}: layout unknown, a base class is missing from the dump", class.name)?;
+                continue;
+            }
+        };
+
+        write_class_comment(out, class, "")?;
+
+        writeln!(out, "struct {} {{", identifier(&class.name))?;
+
+        if let Some(base) = layout.base {
+            writeln!(out, "    struct {} base;", identifier(&base.name))?;
+        }
+
+        for member in &layout.members {
+            match member {
+                Member::Padding { offset, size } => {
+                    writeln!(out, "    unsigned char pad_0x{:04X}[0x{:X}];", offset, size)?;
+                },
+                Member::Field { field, primitive: true, .. } => {
+                    writeln!(out, "    {} {}; // 0x{:X}", c_type(&field.type_name), identifier(&field.name), field.offset)?;
+                },
+                Member::Field { field, size, .. } => {
+                    writeln!(out, "    unsigned char {}[0x{:X}]; // 0x{:X} {}", identifier(&field.name), size, field.offset, field.type_name)?;
+                }
+            }
+        }
+
+        for field in &layout.overlapping {
+            writeln!(out, "    // {} at 0x{:X} overlaps a previous field ({})", field.name, field.offset, field.type_name)?;
+        }
+
+        writeln!(out, "}};")?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "#pragma pack(pop)")
+}
+
+fn c_type(type_name: &str) -> &'static str {
+    match type_name {
+        // C89 has no bool, and _Bool isn't guaranteed to be one byte
+        "bool" => "unsigned char",
+        "char" => "char",
+        "int8" => "signed char",
+        "uint8" => "unsigned char",
+        "int16" => "short",
+        "uint16" => "unsigned short",
+        "int32" => "int",
+        "uint32" => "unsigned int",
+        "int64" => "long long",
+        "uint64" => "unsigned long long",
+        "float32" => "float",
+        "float64" => "double",
+        _ => "void *"
+    }
+}
+
+fn c_integer(repr: &EnumRepr) -> &'static str {
+    match (repr.bits, repr.signed) {
+        (8, false) => "unsigned char",
+        (8, true) => "signed char",
+        (16, false) => "unsigned short",
+        (16, true) => "short",
+        (32, false) => "unsigned int",
+        (32, true) => "int",
+        (_, false) => "unsigned long long",
+        (_, true) => "long long"
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Data,
+    // the number of function pointers, 0 when unknown
+    VTable { functions: usize }
+}
+
+pub struct Symbol {
+    pub name: String,
+    pub rva: usize,
+    pub kind: SymbolKind
+}
+
+// names worth applying to a module's disassembly: offsets that resolved to addresses, interface
+// factories, instances and vtables, and the vtables found through RTTI
+pub fn module_symbols(model: &Model, module: &DumpedModule) -> Vec<Symbol> {
+    let mut symbols: Vec<Symbol> = Vec::new();
+
+    let rva = |address: usize| Some(address.wrapping_sub(module.base)).filter(|rva| *rva < module.size);
+
+    for (name, offset) in model.offsets.get(&module.name).into_iter().flatten() {
+        if let Offset::Rva(rva) = *offset {
+            if rva < module.size {
+                symbols.push(Symbol { name: identifier(name), rva, kind: SymbolKind::Data });
+            }
+        }
+    }
+
+    let vtables: Vec<&VTable> = model.vtables.iter()
+        .filter(|vtables| vtables.module == module.name)
+        .flat_map(|vtables| &vtables.vtables)
+        .filter(|vtable| vtable.rva < module.size)
+        .collect();
+
+    for vtable in &vtables {
+        let name = match vtable.offset {
+            0 => format!("{}_vtable", identifier(&vtable.class_name)),
+            offset => format!("{}_vtable_{:X}", identifier(&vtable.class_name), offset)
+        };
+
+        symbols.push(Symbol { name, rva: vtable.rva, kind: SymbolKind::VTable { functions: vtable.functions } });
+    }
+
+    for interfaces in model.interfaces.iter().filter(|interfaces| interfaces.module == module.name) {
+        for interface in &interfaces.interfaces {
+            let name = identifier(&interface.name);
+
+            if interface.factory_rva < module.size {
+                symbols.push(Symbol { name: format!("{}_factory", name), rva: interface.factory_rva, kind: SymbolKind::Function });
+            }
+
+            if let Some(rva) = interface.instance.and_then(rva) {
+                symbols.push(Symbol { name: format!("{}_instance", name), rva, kind: SymbolKind::Data });
+            }
+
+            // the class name RTTI gave the vtable says more than the interface version
+            if let Some(rva) = interface.vtable.and_then(rva).filter(|rva| !vtables.iter().any(|vtable| vtable.rva == *rva)) {
+                symbols.push(Symbol { name: format!("{}_vtable", name), rva, kind: SymbolKind::VTable { functions: 0 } });
+            }
+        }
+    }
+
+    symbols
+}
+
+// the symbol lists of the disassembler scripts, as python lists of tuples
+pub fn write_python_symbols(out: &mut String, symbols: &[Symbol]) -> fmt::Result {
+    use fmt::Write;
+
+    writeln!(out)?;
+    writeln!(out, "FUNCTIONS = [")?;
+
+    for symbol in symbols.iter().filter(|symbol| symbol.kind == SymbolKind::Function) {
+        writeln!(out, "    (0x{:X}, {}),", symbol.rva, python_string(&symbol.name))?;
+    }
+
+    writeln!(out, "]")?;
+    writeln!(out)?;
+    writeln!(out, "DATA = [")?;
+
+    for symbol in symbols.iter().filter(|symbol| symbol.kind == SymbolKind::Data) {
+        writeln!(out, "    (0x{:X}, {}),", symbol.rva, python_string(&symbol.name))?;
+    }
+
+    writeln!(out, "]")?;
+    writeln!(out)?;
+    writeln!(out, "# rva, name, function count or 0 when unknown")?;
+    writeln!(out, "VTABLES = [")?;
+
+    for symbol in symbols {
+        if let SymbolKind::VTable { functions } = symbol.kind {
+            writeln!(out, "    (0x{:X}, {}, {}),", symbol.rva, python_string(&symbol.name), functions)?;
+        }
+    }
+
+    writeln!(out, "]")
+}
+
+pub fn primitive_size(type_name: &str) -> Option<usize> {
+    match type_name {
+        "bool" | "char" | "int8" | "uint8" => Some(1),
+        "int16" | "uint16" => Some(2),
+        "int32" | "uint32" | "float32" => Some(4),
+        "int64" | "uint64" | "float64" => Some(8),
+        type_name if type_name.ends_with('*') => Some(8),
+        _ => None
+    }
+}
+
+pub fn scope_name(scope: &SchemaScope) -> String {
+    module_name(&scope.name)
+}
+
+// turns a module or scope like "libclient.so" or "!GlobalTypes" into something usable as an identifier and file name
+pub fn module_name(name: &str) -> String {
+    let stem = Path::new(name).with_extension("");
+    identifier(stem.to_str().unwrap_or(name).trim_start_matches(|c: char| !c.is_ascii_alphanumeric()))
+}
+
+pub fn identifier(name: &str) -> String {
+    let identifier: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+
+    match identifier.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("_{}", identifier),
+        _ => identifier
+    }
+}
+
+// size, parents and metadata of a class as `//` comments, shared by the c-like outputs
+pub fn write_class_comment(out: &mut String, class: &SchemaClass, indent: &str) -> fmt::Result {
+    use fmt::Write;
+
+    writeln!(out, "{}// Size: 0x{:X}", indent, class.size)?;
+
+    for base_class in &class.base_classes {
+        writeln!(out, "{}// Parent: {}", indent, base_class)?;
+    }
+
+    for metadata in &class.metadata {
+        writeln!(out, "{}// {}", indent, metadata.name)?;
+    }
+
+    Ok(())
+}
+
+// json string literals are valid python string literals
+pub fn python_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+pub fn write_output(path: &Path, contents: &str) -> Result<()> {
+    create_dir_all(path.parent().unwrap())?;
+
+    File::options()
+        .create(true)
+        .write(true).truncate(true)
+        .open(path)?
+        .write_all(contents.as_bytes())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::schema::SchemaEnumerator;
+    use super::*;
+
+    fn schema_enum(name: &str, size: u8, enumerators: &[(&str, i64)]) -> SchemaEnum {
+        SchemaEnum {
+            name: name.to_string(),
+            module: "client.dll".to_string(),
+            size,
+            is_flags: false,
+            enumerators: enumerators.iter()
+                .map(|(name, value)| SchemaEnumerator { name: name.to_string(), value: *value })
+                .collect()
+        }
+    }
+
+    fn field(name: &str, offset: i32, type_name: &str) -> SchemaField {
+        SchemaField { name: name.to_string(), offset, type_name: type_name.to_string(), metadata: Vec::new() }
+    }
+
+    fn declarations() -> String {
+        let scope = SchemaScope {
+            name: "client.dll".to_string(),
+            classes: vec![SchemaClass {
+                name: "C_Player".to_string(),
+                module: "client.dll".to_string(),
+                size: 0x10,
+                base_classes: Vec::new(),
+                fields: vec![field("m_bAlive", 0x0, "bool"), field("m_iHealth", 0x4, "int32"), field("m_pNext", 0x8, "C_Player*")],
+                metadata: Vec::new()
+            }],
+            enums: vec![
+                schema_enum("Team", 1, &[("TEAM_NONE", 0), ("TEAM_CT", 3)]),
+                schema_enum("Mask", 8, &[("MASK_NONE", 0), ("MASK_HIGH", 1 << 40)]),
+                schema_enum("Other", 1, &[("TEAM_NONE", 0)])
+            ]
+        };
+
+        let scopes = [scope];
+        let index = ClassIndex::new(&scopes);
+        let types = ModuleTypes {
+            classes: scopes[0].classes.iter().collect(),
+            enums: scopes[0].enums.iter().collect()
+        };
+
+        let mut out = String::new();
+        write_c_declarations(&mut out, &types, &index).unwrap();
+        out
+    }
+
+    #[test]
+    fn c_declarations_are_plain_c() {
+        let out = declarations();
+
+        assert!(out.contains("typedef unsigned char Team;\nenum {\n    TEAM_NONE = 0,\n    TEAM_CT = 3,\n};"));
+        assert!(out.contains("typedef unsigned char Other;\nenum {\n    Other__TEAM_NONE = 0,\n};"));
+        assert!(out.contains("    unsigned char m_bAlive; // 0x0"));
+        assert!(!out.contains(" : "));
+    }
+
+    #[test]
+    fn c_declarations_define_enumerators_wider_than_int() {
+        let out = declarations();
+
+        assert!(out.contains("typedef unsigned long long Mask;\n#define MASK_NONE (0ULL)\n#define MASK_HIGH (1099511627776ULL)"));
+    }
+}
@@ -0,0 +1,96 @@
+use std::{fs::{self, create_dir_all, File}, path::Path};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use crate::{error::Result, game::{interfaces::ModuleInterfaces, modules::DumpedModule, offsets::Offsets, schema::SchemaScope, vtables::ModuleVTables}};
+
+// bumped whenever a field is renamed, removed or changes shape, so templates and other consumers can tell
+pub const MODEL_VERSION: u32 = 2;
+
+// everything a run produced, written to output/manifest.json and rendered by user templates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Model {
+    pub version: u32,
+    pub timestamp: String,
+    #[serde(default)]
+    pub modules: Vec<DumpedModule>,
+    #[serde(default)]
+    pub schema: Vec<SchemaScope>,
+    #[serde(default)]
+    pub interfaces: Vec<ModuleInterfaces>,
+    #[serde(default)]
+    pub vtables: Vec<ModuleVTables>,
+    #[serde(default)]
+    pub offsets: Offsets
+}
+
+impl Model {
+    pub fn new(modules: Vec<DumpedModule>, schema: Vec<SchemaScope>, interfaces: Vec<ModuleInterfaces>, vtables: Vec<ModuleVTables>, offsets: Offsets) -> Self {
+        Self {
+            version: MODEL_VERSION,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            modules,
+            schema,
+            interfaces,
+            vtables,
+            offsets
+        }
+    }
+
+    // a manifest, or an output directory; directories written before manifests existed are read
+    // from their per scope and per module files
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.is_dir() {
+            return Ok(serde_json::from_reader(File::open(path)?)?);
+        }
+
+        let manifest = path.join("manifest.json");
+
+        if manifest.is_file() {
+            return Ok(serde_json::from_reader(File::open(manifest)?)?);
+        }
+
+        let offsets = match path.join("offsets.json") {
+            offsets if offsets.is_file() => serde_json::from_reader(File::open(offsets)?)?,
+            _ => Offsets::new()
+        };
+
+        Ok(Self {
+            version: MODEL_VERSION,
+            timestamp: String::new(),
+            modules: Vec::new(),
+            schema: read_directory(&path.join("schema"))?,
+            interfaces: read_directory(&path.join("interfaces"))?,
+            vtables: read_directory(&path.join("vtables"))?,
+            offsets
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        create_dir_all(path.parent().unwrap())?;
+
+        Ok(serde_json::to_writer_pretty(
+            File::options()
+                .create(true)
+                .write(true).truncate(true)
+                .open(path)?,
+            self)?)
+    }
+}
+
+// every json file in a directory, sorted by file name so loads are reproducible
+fn read_directory<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    if !path.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths: Vec<_> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+
+    paths.sort();
+
+    paths.into_iter()
+        .map(|path| Ok(serde_json::from_reader(File::open(path)?)?))
+        .collect()
+}
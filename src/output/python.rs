@@ -0,0 +1,271 @@
+use std::{collections::{HashMap, HashSet}, fmt::{Result, Write}, path::Path};
+use crate::game::{offsets::Offsets, schema::{SchemaClass, SchemaEnum, SchemaScope}};
+use super::{identifier, python_string, write_output, ClassIndex, EnumRepr, Layout, Member};
+
+const KEYWORDS: [&str; 35] = [
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+    "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal",
+    "not", "or", "pass", "raise", "return", "try", "while", "with", "yield"
+];
+
+// everything ends up in one module, so analysts only have to import a single file
+pub fn write(scopes: &[SchemaScope], offsets: &Offsets) {
+    let path = Path::new("output/python/cs2_schema.py");
+
+    match generate(scopes, offsets).map_err(Into::into).and_then(|source| write_output(path, &source)) {
+        Ok(_) => println!("generated python module: {}", path.display()),
+        Err(err) => println!("failed to generate python module: {}, error: {}", path.display(), err)
+    }
+}
+
+fn generate(scopes: &[SchemaScope], offsets: &Offsets) -> std::result::Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(out, "# Generated by cs2-dumper")?;
+    writeln!(out, "#")?;
+    writeln!(out, "# Structures are packed with every gap spelled out, map a memory blob with")?;
+    writeln!(out, "# e.g. `C_BaseEntity.from_buffer_copy(data)`.")?;
+    writeln!(out)?;
+    writeln!(out, "import ctypes")?;
+    writeln!(out, "import enum")?;
+
+    // enums and classes share the module namespace, a type whose name is already taken gets a numbered one
+    let mut names: HashSet<String> = HashSet::new();
+
+    // scopes can define the same type, the first definition wins like it does for base class lookups
+    let mut enums: HashSet<&str> = HashSet::new();
+
+    for scope in scopes {
+        for schema_enum in &scope.enums {
+            if enums.insert(&schema_enum.name) {
+                let name = unique_name(&mut names, python_identifier(&schema_enum.name));
+
+                writeln!(out)?;
+                writeln!(out)?;
+                write_enum(&mut out, scope, schema_enum, &name)?;
+            }
+        }
+    }
+
+    let index = ClassIndex::new(scopes);
+
+    // python needs base classes defined first, which holds when sorting by the length of the inheritance chain
+    let mut classes: Vec<(usize, &SchemaScope, &SchemaClass, Layout)> = scopes.iter()
+        .flat_map(|scope| scope.classes.iter().map(move |class| (scope, class)))
+        .filter_map(|(scope, class)| Layout::new(class, &index).map(|layout| (index.depth(class), scope, class, layout)))
+        .collect();
+
+    classes.sort_by_key(|(depth, ..)| *depth);
+
+    // schema class name -> python name, for subclasses to derive from
+    let mut class_names: HashMap<&str, String> = HashMap::new();
+
+    for (_, scope, class, layout) in &classes {
+        if !index.get(&class.name).is_some_and(|(_, indexed)| std::ptr::eq(indexed, *class)) {
+            continue;
+        }
+
+        // ctypes appends the fields of a subclass after the ones of its base
+        let base = match layout.base {
+            Some(base) => match class_names.get(base.name.as_str()) {
+                Some(base) => base.clone(),
+                None => continue
+            },
+            None => "ctypes.Structure".to_string()
+        };
+
+        let name = unique_name(&mut names, python_identifier(&class.name));
+
+        writeln!(out)?;
+        writeln!(out)?;
+        write_structure(&mut out, scope, class, layout, &name, &base)?;
+
+        class_names.insert(&class.name, name);
+    }
+
+    writeln!(out)?;
+    writeln!(out)?;
+    writeln!(out, "# module -> name -> offset, resolved from the configured signatures")?;
+    writeln!(out, "OFFSETS = {{")?;
+
+    for (module, offsets) in offsets {
+        writeln!(out, "    {}: {{", python_string(module))?;
+
+        for (name, offset) in offsets {
+            writeln!(out, "        {}: 0x{:X},", python_string(name), offset)?;
+        }
+
+        writeln!(out, "    }},")?;
+    }
+
+    writeln!(out, "}}")?;
+
+    writeln!(out)?;
+    writeln!(out, "# class -> field -> offset, including classes without a known layout")?;
+    writeln!(out, "SCHEMA_OFFSETS = {{")?;
+
+    for scope in scopes {
+        for class in &scope.classes {
+            writeln!(out, "    {}: {{", python_string(&class.name))?;
+
+            let mut fields: Vec<_> = class.fields.iter().collect();
+            fields.sort_by_key(|field| field.offset);
+
+            for field in fields {
+                writeln!(out, "        {}: 0x{:X},", python_string(&field.name), field.offset)?;
+            }
+
+            writeln!(out, "    }},")?;
+        }
+    }
+
+    writeln!(out, "}}")?;
+
+    Ok(out)
+}
+
+fn write_enum(out: &mut String, scope: &SchemaScope, schema_enum: &SchemaEnum, name: &str) -> Result {
+    let repr = EnumRepr::new(schema_enum);
+
+    let base = match schema_enum.is_flags {
+        true => "enum.IntFlag",
+        false => "enum.IntEnum"
+    };
+
+    writeln!(out, "class {}({}):", name, base)?;
+    writeln!(out, "    # Scope: {}", scope.name)?;
+    writeln!(out, "    # Size: 0x{:X}", schema_enum.size)?;
+
+    for enumerator in &schema_enum.enumerators {
+        let value = repr.value(enumerator.value);
+
+        let value = match schema_enum.is_flags && value >= 0 {
+            true => format!("0x{:X}", value),
+            false => value.to_string()
+        };
+
+        writeln!(out, "    {} = {}", python_identifier(&enumerator.name), value)?;
+    }
+
+    if schema_enum.enumerators.is_empty() {
+        writeln!(out, "    pass")?;
+    }
+
+    Ok(())
+}
+
+fn write_structure(out: &mut String, scope: &SchemaScope, class: &SchemaClass, layout: &Layout, name: &str, base: &str) -> Result {
+    writeln!(out, "class {}({}):", name, base)?;
+    writeln!(out, "    # Scope: {}", scope.name)?;
+    writeln!(out, "    # Size: 0x{:X}", class.size)?;
+
+    for base_class in &class.base_classes {
+        writeln!(out, "    # Parent: {}", base_class)?;
+    }
+
+    for metadata in &class.metadata {
+        writeln!(out, "    # {}", metadata.name)?;
+    }
+
+    for field in &layout.overlapping {
+        writeln!(out, "    # {} at 0x{:X} overlaps a previous field ({})", field.name, field.offset, field.type_name)?;
+    }
+
+    writeln!(out, "    _pack_ = 1")?;
+    writeln!(out, "    _layout_ = \"ms\"")?;
+    writeln!(out, "    _fields_ = [")?;
+
+    for member in &layout.members {
+        match member {
+            Member::Padding { offset, size } => {
+                writeln!(out, "        (\"pad_0x{:04X}\", ctypes.c_uint8 * 0x{:X}),", offset, size)?;
+            },
+            Member::Field { field, primitive: true, .. } => {
+                writeln!(out, "        ({}, {}),  # 0x{:X}", python_string(&python_identifier(&field.name)), ctypes_type(&field.type_name), field.offset)?;
+            },
+            Member::Field { field, size, .. } => {
+                writeln!(out, "        ({}, ctypes.c_uint8 * 0x{:X}),  # 0x{:X} {}", python_string(&python_identifier(&field.name)), size, field.offset, field.type_name)?;
+            }
+        }
+    }
+
+    writeln!(out, "    ]")
+}
+
+fn ctypes_type(type_name: &str) -> &'static str {
+    match type_name {
+        "bool" => "ctypes.c_bool",
+        "char" => "ctypes.c_int8",
+        "int8" => "ctypes.c_int8",
+        "uint8" => "ctypes.c_uint8",
+        "int16" => "ctypes.c_int16",
+        "uint16" => "ctypes.c_uint16",
+        "int32" => "ctypes.c_int32",
+        "uint32" => "ctypes.c_uint32",
+        "int64" => "ctypes.c_int64",
+        "uint64" => "ctypes.c_uint64",
+        "float32" => "ctypes.c_float",
+        "float64" => "ctypes.c_double",
+        // pointers into the game, always 64 bit regardless of the host
+        _ => "ctypes.c_uint64"
+    }
+}
+
+fn unique_name(names: &mut HashSet<String>, name: String) -> String {
+    let mut unique = name.clone();
+
+    let mut i = 2;
+    while !names.insert(unique.clone()) {
+        unique = format!("{}_{}", name, i);
+        i += 1;
+    }
+
+    unique
+}
+
+fn python_identifier(name: &str) -> String {
+    let name = identifier(name);
+
+    match KEYWORDS.contains(&name.as_str()) {
+        true => format!("{}_", name),
+        false => name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::schema::{SchemaEnumerator, SchemaField};
+    use super::*;
+
+    fn class(name: &str, size: i32, base_classes: &[&str]) -> SchemaClass {
+        SchemaClass {
+            name: name.to_string(),
+            module: "client.dll".to_string(),
+            size,
+            base_classes: base_classes.iter().map(|name| name.to_string()).collect(),
+            fields: vec![SchemaField { name: format!("m_n{}", name), offset: size - 4, type_name: "int32".to_string(), metadata: Vec::new() }],
+            metadata: Vec::new()
+        }
+    }
+
+    #[test]
+    fn classes_named_like_enums_are_renamed() {
+        let scopes = [SchemaScope {
+            name: "client.dll".to_string(),
+            classes: vec![class("Entity", 0x8, &[]), class("Player", 0x10, &["Entity"])],
+            enums: vec![SchemaEnum {
+                name: "Entity".to_string(),
+                module: "client.dll".to_string(),
+                size: 4,
+                is_flags: false,
+                enumerators: vec![SchemaEnumerator { name: "ENTITY_NONE".to_string(), value: 0 }]
+            }]
+        }];
+
+        let out = generate(&scopes, &Offsets::new()).unwrap();
+
+        assert!(out.contains("class Entity(enum.IntEnum):"));
+        assert!(out.contains("class Entity_2(ctypes.Structure):"));
+        assert!(out.contains("class Player(Entity_2):"));
+    }
+}
@@ -0,0 +1,297 @@
+use std::{fmt::{Result, Write}, path::Path};
+use crate::game::{offsets::Offsets, schema::{SchemaClass, SchemaEnum, SchemaScope}};
+use super::{identifier, module_name, scope_name, write_class_comment, write_output, ClassIndex, EnumRepr, Layout, Member};
+
+const KEYWORDS: [&str; 48] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match",
+    "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static", "struct", "trait", "true", "try",
+    "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield"
+];
+
+// offset_of! was stabilized in 1.77, the empty workspace keeps cargo from treating the crate
+// as part of a workspace it happens to be generated in
+const MANIFEST: &str = r#"# Generated by cs2-dumper
+
+[package]
+name = "cs2-dumper-output"
+version = "0.1.0"
+edition = "2021"
+rust-version = "1.77"
+
+[lib]
+name = "cs2_dumper"
+
+[workspace]
+"#;
+
+// written as a standalone crate so it can be used as a path or git dependency as is
+pub fn write(scopes: &[SchemaScope], offsets: &Offsets) {
+    let root = Path::new("output/rust");
+
+    let index = ClassIndex::new(scopes);
+
+    let mut files: Vec<(String, std::result::Result<String, std::fmt::Error>)> = vec![
+        ("Cargo.toml".to_string(), Ok(MANIFEST.to_string())),
+        ("src/lib.rs".to_string(), generate_lib(scopes)),
+        ("src/offsets.rs".to_string(), generate_offsets(offsets))
+    ];
+
+    for scope in scopes {
+        files.push((format!("src/schemas/{}.rs", scope_name(scope)), generate_scope(scope, &index)));
+    }
+
+    for (file, contents) in files {
+        match contents.map_err(Into::into).and_then(|contents| write_output(&root.join(&file), &contents)) {
+            Ok(_) => (),
+            Err(err) => {
+                println!("failed to generate rust crate file: {}, error: {}", file, err);
+                return;
+            }
+        }
+    }
+
+    println!("generated rust crate: {}", root.display());
+}
+
+fn generate_lib(scopes: &[SchemaScope]) -> std::result::Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(out, "// Generated by cs2-dumper")?;
+    writeln!(out)?;
+    writeln!(out, "pub mod offsets;")?;
+    writeln!(out)?;
+    writeln!(out, "pub mod schemas {{")?;
+
+    for scope in scopes {
+        writeln!(out, "    pub mod {};", scope_name(scope))?;
+    }
+
+    writeln!(out, "}}")?;
+
+    Ok(out)
+}
+
+fn generate_offsets(offsets: &Offsets) -> std::result::Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(out, "// Generated by cs2-dumper from offsets.json")?;
+    writeln!(out)?;
+    writeln!(out, "#![allow(non_snake_case, non_upper_case_globals)]")?;
+
+    for (module, offsets) in offsets {
+        writeln!(out)?;
+        writeln!(out, "// {}", module)?;
+        writeln!(out, "pub mod {} {{", rust_identifier(&module_name(module)))?;
+
+        for (name, offset) in offsets {
+            writeln!(out, "    pub const {}: usize = 0x{:X};", rust_identifier(name), offset)?;
+        }
+
+        writeln!(out, "}}")?;
+    }
+
+    Ok(out)
+}
+
+fn generate_scope(scope: &SchemaScope, index: &ClassIndex) -> std::result::Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(out, "// Generated by cs2-dumper from schema scope {}", scope.name)?;
+    writeln!(out)?;
+    writeln!(out, "#![allow(non_snake_case, non_camel_case_types, non_upper_case_globals, dead_code)]")?;
+
+    writeln!(out)?;
+    write_offsets(&mut out, scope)?;
+
+    for schema_enum in &scope.enums {
+        writeln!(out)?;
+        write_enum(&mut out, schema_enum)?;
+    }
+
+    let mut layouts = Vec::new();
+
+    for class in index.sorted_by_inheritance(scope) {
+        writeln!(out)?;
+
+        match Layout::new(class, index) {
+            Some(layout) => {
+                write_struct(&mut out, class, &layout, index)?;
+                layouts.push((class, layout));
+            },
+            None => writeln!(out, "// {}: layout unknown, a base class is missing from the dump", class.name)?
+        }
+    }
+
+    if !layouts.is_empty() {
+        writeln!(out)?;
+        write_tests(&mut out, &layouts)?;
+    }
+
+    Ok(out)
+}
+
+fn write_offsets(out: &mut String, scope: &SchemaScope) -> Result {
+    writeln!(out, "pub mod offsets {{")?;
+
+    for (i, class) in scope.classes.iter().enumerate() {
+        if i != 0 {
+            writeln!(out)?;
+        }
+
+        write_class_comment(out, class, "    ")?;
+
+        writeln!(out, "    pub mod {} {{", rust_identifier(&class.name))?;
+
+        let mut fields: Vec<_> = class.fields.iter().collect();
+        fields.sort_by_key(|field| field.offset);
+
+        for field in fields {
+            writeln!(out, "        pub const {}: usize = 0x{:X}; // {}", rust_identifier(&field.name), field.offset, field.type_name)?;
+        }
+
+        writeln!(out, "    }}")?;
+    }
+
+    writeln!(out, "}}")
+}
+
+// newtypes rather than rust enums, values read from the game aren't guaranteed to be one of the enumerators
+// and enumerators may share a value
+fn write_enum(out: &mut String, schema_enum: &SchemaEnum) -> Result {
+    let repr = EnumRepr::new(schema_enum);
+
+    let name = rust_identifier(&schema_enum.name);
+
+    writeln!(out, "// Size: 0x{:X}", schema_enum.size)?;
+    writeln!(out, "#[repr(transparent)]")?;
+    writeln!(out, "#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]")?;
+    writeln!(out, "pub struct {}(pub {}{});", name, if repr.signed { "i" } else { "u" }, repr.bits)?;
+    writeln!(out)?;
+    writeln!(out, "impl {} {{", name)?;
+
+    for enumerator in &schema_enum.enumerators {
+        let value = repr.value(enumerator.value);
+
+        let value = match schema_enum.is_flags && value >= 0 {
+            true => format!("0x{:X}", value),
+            false => value.to_string()
+        };
+
+        writeln!(out, "    pub const {}: Self = Self({});", rust_identifier(&enumerator.name), value)?;
+    }
+
+    writeln!(out, "}}")?;
+
+    if schema_enum.is_flags {
+        writeln!(out)?;
+        writeln!(out, "impl {} {{", name)?;
+        writeln!(out, "    pub const fn contains(self, other: Self) -> bool {{")?;
+        writeln!(out, "        self.0 & other.0 == other.0")?;
+        writeln!(out, "    }}")?;
+        writeln!(out, "}}")?;
+
+        for (op, method, operator) in [("BitOr", "bitor", "|"), ("BitAnd", "bitand", "&")] {
+            writeln!(out)?;
+            writeln!(out, "impl core::ops::{} for {} {{", op, name)?;
+            writeln!(out, "    type Output = Self;")?;
+            writeln!(out)?;
+            writeln!(out, "    fn {}(self, other: Self) -> Self {{", method)?;
+            writeln!(out, "        Self(self.0 {} other.0)", operator)?;
+            writeln!(out, "    }}")?;
+            writeln!(out, "}}")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_struct(out: &mut String, class: &SchemaClass, layout: &Layout, index: &ClassIndex) -> Result {
+    write_class_comment(out, class, "")?;
+
+    // packed since schema offsets are exact and fields don't have to be naturally aligned
+    writeln!(out, "#[repr(C, packed)]")?;
+    writeln!(out, "#[derive(Clone, Copy)]")?;
+    writeln!(out, "pub struct {} {{", rust_identifier(&class.name))?;
+
+    if let Some((base_scope, base)) = layout.base.and_then(|base| index.get(&base.name)) {
+        writeln!(out, "    pub base: crate::schemas::{}::{}, // 0x0", scope_name(base_scope), rust_identifier(&base.name))?;
+    }
+
+    for member in &layout.members {
+        match member {
+            Member::Padding { offset, size } => {
+                writeln!(out, "    pad_0x{:04X}: [u8; 0x{:X}],", offset, size)?;
+            },
+            Member::Field { field, primitive: true, .. } => {
+                writeln!(out, "    pub {}: {}, // 0x{:X}", rust_identifier(&field.name), rust_type(&field.type_name), field.offset)?;
+            },
+            Member::Field { field, size, .. } => {
+                writeln!(out, "    pub {}: [u8; 0x{:X}], // 0x{:X} {}", rust_identifier(&field.name), size, field.offset, field.type_name)?;
+            }
+        }
+    }
+
+    for field in &layout.overlapping {
+        writeln!(out, "    // {} at 0x{:X} overlaps a previous field ({})", field.name, field.offset, field.type_name)?;
+    }
+
+    writeln!(out, "}}")
+}
+
+fn write_tests(out: &mut String, layouts: &[(&SchemaClass, Layout)]) -> Result {
+    writeln!(out, "#[cfg(test)]")?;
+    writeln!(out, "mod tests {{")?;
+    writeln!(out, "    use super::*;")?;
+    writeln!(out, "    use core::mem::{{offset_of, size_of}};")?;
+
+    for (class, layout) in layouts {
+        let name = rust_identifier(&class.name);
+
+        writeln!(out)?;
+        writeln!(out, "    #[test]")?;
+        writeln!(out, "    fn {}_layout() {{", identifier(&class.name))?;
+        writeln!(out, "        assert_eq!(size_of::<{}>(), 0x{:X});", name, class.size)?;
+
+        for member in &layout.members {
+            if let Member::Field { field, .. } = member {
+                let field_name = rust_identifier(&field.name);
+                writeln!(out, "        assert_eq!(offset_of!({}, {}), offsets::{}::{});", name, field_name, name, field_name)?;
+            }
+        }
+
+        writeln!(out, "    }}")?;
+    }
+
+    writeln!(out, "}}")
+}
+
+
+fn rust_type(type_name: &str) -> &'static str {
+    match type_name {
+        "bool" => "bool",
+        "char" => "i8",
+        "int8" => "i8",
+        "uint8" => "u8",
+        "int16" => "i16",
+        "uint16" => "u16",
+        "int32" => "i32",
+        "uint32" => "u32",
+        "int64" => "i64",
+        "uint64" => "u64",
+        "float32" => "f32",
+        "float64" => "f64",
+        // pointers into the game, always 64 bit regardless of the host
+        _ => "u64"
+    }
+}
+
+fn rust_identifier(name: &str) -> String {
+    let name = identifier(name);
+
+    match name.as_str() {
+        "self" | "Self" | "super" | "crate" | "_" => format!("{}_", name),
+        name if KEYWORDS.contains(&name) => format!("r#{}", name),
+        _ => name
+    }
+}
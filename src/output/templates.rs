@@ -0,0 +1,70 @@
+use std::{collections::BTreeMap, error::Error as _, path::{Component, Path}};
+use minijinja::{path_loader, Environment, UndefinedBehavior, Value};
+use super::{identifier, model::Model, module_name, write_output};
+
+const TEMPLATE_DIRECTORY: &str = "templates";
+
+// renders templates/<template> into output/<path> for every configured pair
+pub fn render(model: &Model, templates: &BTreeMap<String, String>) {
+    if templates.is_empty() {
+        return;
+    }
+
+    let mut env = Environment::new();
+
+    env.set_loader(path_loader(TEMPLATE_DIRECTORY));
+    // a typo in a template should fail loudly instead of rendering nothing
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+
+    env.add_filter("hex", hex);
+    env.add_filter("identifier", |value: &str| identifier(value));
+    env.add_filter("module_name", |value: &str| module_name(value));
+
+    let context = Value::from_serialize(model);
+
+    for (template, path) in templates {
+        // output paths are kept inside the output directory
+        if Path::new(path).components().any(|component| !matches!(component, Component::Normal(_))) {
+            println!("invalid template output path: {}", path);
+            continue;
+        }
+
+        let path = Path::new("output").join(path);
+
+        let rendered = match env.get_template(template).and_then(|template| template.render(&context)) {
+            Ok(rendered) => rendered,
+            Err(err) => {
+                println!("failed to render template: {}, error: {}", template, describe(&err));
+                continue
+            }
+        };
+
+        match write_output(&path, &rendered) {
+            Ok(_) => println!("rendered template: {} -> {}", template, path.display()),
+            Err(err) => println!("failed to write template output: {}, error: {}", path.display(), err)
+        }
+    }
+}
+
+// errors raised inside included templates or filters are kept as sources
+fn describe(err: &minijinja::Error) -> String {
+    let mut description = err.to_string();
+
+    let mut source = err.source();
+    while let Some(err) = source {
+        description.push_str(": ");
+        description.push_str(&err.to_string());
+        source = err.source();
+    }
+
+    description
+}
+
+// `{{ offset | hex }}` -> 0x1A2B
+fn hex(value: Value) -> Result<String, minijinja::Error> {
+    match (u64::try_from(value.clone()), i64::try_from(value.clone())) {
+        (Ok(value), _) => Ok(format!("0x{:X}", value)),
+        (Err(_), Ok(value)) => Ok(format!("-0x{:X}", value.unsigned_abs())),
+        _ => Err(minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, format!("hex expects an integer, got {}", value)))
+    }
+}
@@ -0,0 +1,278 @@
+use std::{fmt, ops::Range, str::FromStr};
+use serde::{Deserialize, Serialize};
+use crate::{error::{Error, Result}, platform::{ProcessModule, ProcessTrait}};
+
+const SCAN_CHUNK_SIZE: usize = 0x100000;
+const PAGE_SIZE: usize = 0x1000;
+
+// IDA-style byte pattern, e.g. "48 8B 05 ? ? ? ? 48 85 C0" where `?`/`??` matches any byte
+// and a single `?` nibble like `4?` or `?5` only matches half of one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    bytes: Vec<u8>,
+    mask: Vec<u8>
+}
+
+impl Pattern {
+    // a mask byte of 0xFF keeps the byte, 0x00 matches anything
+    pub fn new(bytes: Vec<u8>, mask: Vec<u8>) -> Self {
+        let bytes = bytes.iter().zip(&mask).map(|(byte, mask)| byte & mask).collect();
+        Self { bytes, mask }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() >= self.bytes.len() && self.bytes.iter()
+            .zip(&self.mask)
+            .zip(data)
+            .all(|((byte, mask), data)| data & mask == *byte)
+    }
+
+    // fraction of the fully known bytes that `data` agrees with
+    pub fn similarity(&self, data: &[u8]) -> f32 {
+        let known: Vec<(&u8, &u8)> = self.bytes.iter().zip(data)
+            .zip(&self.mask)
+            .filter(|(_, mask)| **mask == 0xFF)
+            .map(|(pair, _)| pair)
+            .collect();
+
+        match known.len() {
+            0 => 0.0,
+            count => known.iter().filter(|(byte, data)| byte == data).count() as f32 / count as f32
+        }
+    }
+
+    pub fn scan(&self, data: &[u8]) -> Vec<usize> {
+        if self.is_empty() || data.len() < self.len() {
+            return Vec::new();
+        }
+
+        // jump between occurrences of the first fully known byte instead of testing every offset
+        let anchor = self.mask.iter().position(|mask| *mask == 0xFF);
+
+        let last = data.len() - self.len();
+
+        match anchor {
+            Some(anchor) => data[anchor..=last + anchor].iter()
+                .enumerate()
+                .filter(|(_, byte)| **byte == self.bytes[anchor])
+                .map(|(offset, _)| offset)
+                .filter(|&offset| self.matches(&data[offset..]))
+                .collect(),
+            None => (0..=last).collect()
+        }
+    }
+
+    // unreadable memory inside the module, like guard pages, is skipped and reported rather than failing the scan
+    pub fn scan_module(&self, process: &impl ProcessTrait, module: &ProcessModule) -> Result<Vec<usize>> {
+        let mut matches: Vec<usize> = Vec::new();
+        let mut skipped: Vec<Range<usize>> = Vec::new();
+
+        // the tail of the previous readable block, so matches across block borders aren't missed
+        let mut window: Vec<u8> = Vec::new();
+        let mut window_end = module.module_base;
+
+        let mut scan_block = |address: usize, data: &[u8]| {
+            if address != window_end {
+                window.clear();
+            }
+
+            window.extend_from_slice(data);
+            window_end = address + data.len();
+
+            let window_start = window_end - window.len();
+            matches.extend(self.scan(&window).into_iter().map(|found| window_start + found));
+
+            // what's kept is shorter than the pattern, so nothing is matched twice
+            window.drain(..window.len().saturating_sub(self.len().saturating_sub(1)));
+        };
+
+        let mut offset = 0;
+        while offset < module.module_size {
+            let size = SCAN_CHUNK_SIZE.min(module.module_size - offset);
+
+            read_readable(process, module.module_base + offset, size, &mut scan_block, &mut skipped);
+
+            offset += size;
+        }
+
+        for range in &skipped {
+            println!("skipped unreadable memory: 0x{:X}-0x{:X}", range.start, range.end);
+        }
+
+        Ok(matches)
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(pattern: &str) -> Result<Self> {
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut mask: Vec<u8> = Vec::new();
+
+        for token in pattern.split_whitespace() {
+            let (byte, byte_mask) = match token.as_bytes() {
+                [b'?'] | [b'?', b'?'] => (0, 0),
+                [high, low] => match (parse_nibble(*high), parse_nibble(*low)) {
+                    (Some((high, high_mask)), Some((low, low_mask))) => (high << 4 | low, high_mask << 4 | low_mask),
+                    _ => return Err(Error::InvalidPattern(pattern.to_string()))
+                },
+                _ => return Err(Error::InvalidPattern(pattern.to_string()))
+            };
+
+            bytes.push(byte);
+            mask.push(byte_mask);
+        }
+
+        if bytes.is_empty() {
+            return Err(Error::InvalidPattern(pattern.to_string()));
+        }
+
+        Ok(Self { bytes, mask })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tokens: Vec<String> = self.bytes.iter().zip(&self.mask)
+            .map(|(byte, mask)| match mask {
+                0x00 => "?".to_string(),
+                0xFF => format!("{:02X}", byte),
+                0xF0 => format!("{:X}?", byte >> 4),
+                _ => format!("?{:X}", byte & 0xF)
+            })
+            .collect();
+
+        write!(f, "{}", tokens.join(" "))
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// a range that fails to read is split in half until the readable pages in it are found
+fn read_readable(process: &impl ProcessTrait, address: usize, size: usize, found: &mut impl FnMut(usize, &[u8]), skipped: &mut Vec<Range<usize>>) {
+    let mut data = vec![0u8; size];
+
+    if process.mem_read(address, &mut data).is_ok() {
+        found(address, &data);
+        return;
+    }
+
+    if size <= PAGE_SIZE {
+        match skipped.last_mut() {
+            Some(last) if last.end == address => last.end = address + size,
+            _ => skipped.push(address..address + size)
+        }
+        return;
+    }
+
+    let half = (size / 2).next_multiple_of(PAGE_SIZE);
+
+    read_readable(process, address, half, found, skipped);
+    read_readable(process, address + half, size - half, found, skipped);
+}
+
+fn parse_nibble(c: u8) -> Option<(u8, u8)> {
+    match c {
+        b'?' => Some((0, 0)),
+        _ => (c as char).to_digit(16).map(|value| (value as u8, 0xF))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::platform::tests::Memory;
+    use super::*;
+
+    #[test]
+    fn from_str_parses_bytes_and_wildcards() {
+        let pattern: Pattern = "48 8B 05 ? ?? C0".parse().unwrap();
+
+        assert_eq!(pattern.bytes, [0x48, 0x8B, 0x05, 0, 0, 0xC0]);
+        assert_eq!(pattern.mask, [0xFF, 0xFF, 0xFF, 0, 0, 0xFF]);
+        assert_eq!(pattern.to_string(), "48 8B 05 ? ? C0");
+    }
+
+    #[test]
+    fn from_str_rejects_invalid_tokens() {
+        for pattern in ["", "48 8G", "488B", "48 ???"] {
+            assert!(matches!(pattern.parse::<Pattern>(), Err(Error::InvalidPattern(_))), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn wildcard_nibbles_match_half_a_byte() {
+        let pattern: Pattern = "4? ?5".parse().unwrap();
+
+        assert_eq!(pattern.mask, [0xF0, 0x0F]);
+        assert_eq!(pattern.to_string(), "4? ?5");
+
+        assert!(pattern.matches(&[0x40, 0x05]));
+        assert!(pattern.matches(&[0x4F, 0xF5]));
+        assert!(!pattern.matches(&[0x50, 0x05]));
+        assert!(!pattern.matches(&[0x40, 0x06]));
+    }
+
+    #[test]
+    fn matches_needs_the_whole_pattern() {
+        let pattern: Pattern = "E8 ? ? ? ? 90".parse().unwrap();
+
+        assert!(pattern.matches(&[0xE8, 1, 2, 3, 4, 0x90, 0xCC]));
+        assert!(!pattern.matches(&[0xE8, 1, 2, 3, 4]));
+        assert!(!pattern.matches(&[0xE9, 1, 2, 3, 4, 0x90]));
+    }
+
+    #[test]
+    fn scan_finds_overlapping_matches() {
+        let pattern: Pattern = "AA ? AA".parse().unwrap();
+
+        assert_eq!(pattern.scan(&[0xAA, 0xAA, 0xAA, 0xAA, 0x00]), [0, 1]);
+    }
+
+    #[test]
+    fn scan_module_finds_matches_across_chunks() {
+        let mut data = vec![0u8; SCAN_CHUNK_SIZE * 2];
+        data[SCAN_CHUNK_SIZE - 2..SCAN_CHUNK_SIZE + 2].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        data[0x10..0x14].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+
+        let process = Memory::new(0x10000, data);
+        let pattern: Pattern = "DE AD ? EF".parse().unwrap();
+
+        assert_eq!(pattern.scan_module(&process, &process.module()).unwrap(), [0x10010, 0x10000 + SCAN_CHUNK_SIZE - 2]);
+    }
+
+    #[test]
+    fn scan_module_skips_unreadable_pages() {
+        let mut data = vec![0u8; SCAN_CHUNK_SIZE];
+        data[0x100..0x104].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        // cut off by the hole that follows
+        data[0x4FFE..0x5000].copy_from_slice(&[0xDE, 0xAD]);
+        data[0x7000..0x7002].copy_from_slice(&[0xBE, 0xEF]);
+        data[0x8000..0x8004].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+
+        let mut process = Memory::new(0x10000, data);
+        process.holes.push(0x15000..0x17000);
+
+        let pattern: Pattern = "DE AD BE EF".parse().unwrap();
+
+        assert_eq!(pattern.scan_module(&process, &process.module()).unwrap(), [0x10100, 0x18000]);
+    }
+}
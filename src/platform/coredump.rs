@@ -0,0 +1,414 @@
+use std::{collections::HashMap, fs::File, mem::size_of, path::Path};
+use crate::{error::{Error, Result}, image::{self, elf::{Elf64_Ehdr, Elf64_Nhdr, Elf64_Phdr, ELFMAGIC, ET_CORE, NT_FILE, PT_LOAD, PT_NOTE}}};
+use super::{image_exports, read_file, read_struct, ProcessExport, ProcessModule, ProcessTrait};
+
+// bytes at the start of a mapped file compared against what the core kept of it
+const KEPT_HEADER_LENGTH: usize = 0x1000;
+
+struct Segment {
+    address: usize,
+    size: usize,
+    offset: usize,
+    // cores leave out whatever the coredump filter excluded, usually the file backed read only mappings
+    file_size: usize
+}
+
+// a file mapped into the crashed process, from the NT_FILE note
+struct Mapping {
+    start: usize,
+    end: usize,
+    offset: usize,
+    path: String,
+    // whether the file at `path` on this machine is the one the process had mapped
+    verified: bool
+}
+
+// memory and mapped files of an ELF core, e.g. from gcore or systemd-coredump
+pub struct Process {
+    file: File,
+    segments: Vec<Segment>,
+    mappings: Vec<Mapping>
+}
+
+impl Process {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let file_size = file.metadata()?.len() as usize;
+
+        let ehdr: Elf64_Ehdr = read_struct(&file, 0)?;
+
+        if ehdr.e_ident[0..4] != ELFMAGIC || ehdr.e_type != ET_CORE {
+            return Err(Error::InvalidImage);
+        }
+
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut mappings: Vec<Mapping> = Vec::new();
+
+        for i in 0..ehdr.e_phnum as usize {
+            let phdr_offset = i.checked_mul(ehdr.e_phentsize as usize)
+                .and_then(|offset| offset.checked_add(ehdr.e_phoff as usize))
+                .ok_or(Error::InvalidImage)?;
+
+            let phdr: Elf64_Phdr = read_struct(&file, phdr_offset)?;
+
+            // whatever the core claims to hold has to be in the file
+            match (phdr.p_offset as usize).checked_add(phdr.p_filesz as usize) {
+                Some(end) if end <= file_size => (),
+                _ if phdr.p_type == PT_LOAD || phdr.p_type == PT_NOTE => return Err(Error::InvalidImage),
+                _ => continue
+            }
+
+            match phdr.p_type {
+                PT_LOAD => {
+                    if (phdr.p_vaddr as usize).checked_add(phdr.p_memsz as usize).is_none() || phdr.p_filesz > phdr.p_memsz {
+                        return Err(Error::InvalidImage);
+                    }
+
+                    segments.push(Segment {
+                        address: phdr.p_vaddr as usize,
+                        size: phdr.p_memsz as usize,
+                        offset: phdr.p_offset as usize,
+                        file_size: phdr.p_filesz as usize
+                    });
+                },
+                PT_NOTE => {
+                    let mut notes = vec![0u8; phdr.p_filesz as usize];
+                    read_file(&file, phdr.p_offset as usize, &mut notes)?;
+
+                    mappings.extend(file_mappings(&notes)?);
+                },
+                _ => ()
+            }
+        }
+
+        segments.sort_by_key(|segment| segment.address);
+
+        let mut process = Self {
+            file,
+            segments,
+            mappings
+        };
+
+        let mut verified: HashMap<String, bool> = HashMap::new();
+
+        for i in 0..process.mappings.len() {
+            let path = process.mappings[i].path.clone();
+            let matches = *verified.entry(path).or_insert_with_key(|path| process.host_file_matches(path));
+
+            process.mappings[i].verified = matches;
+        }
+
+        Ok(process)
+    }
+
+    // the coredump filter keeps the first page of mapped ELF files by default, so the header, the program headers
+    // and usually the build-id note the core kept have to be the same in the file on this machine
+    fn host_file_matches(&self, path: &str) -> bool {
+        let start = match self.mappings.iter().find(|mapping| mapping.path == path && mapping.offset == 0) {
+            Some(mapping) => mapping.start,
+            None => return false
+        };
+
+        let segment = match self.segments.iter().find(|segment| segment.address == start) {
+            Some(segment) => segment,
+            None => return false
+        };
+
+        let mut kept = vec![0u8; segment.file_size.min(KEPT_HEADER_LENGTH)];
+
+        if kept.len() < size_of::<Elf64_Ehdr>() || read_file(&self.file, segment.offset, &mut kept).is_err() || kept[0..4] != ELFMAGIC {
+            return false;
+        }
+
+        let mut host = vec![0u8; kept.len()];
+
+        match File::open(path) {
+            Ok(file) => read_file(&file, 0, &mut host).is_ok() && host == kept,
+            Err(_) => false
+        }
+    }
+
+    // bytes the core left out are read from the mapped file when it's the same file on this machine
+    fn read_segment(&self, segment: &Segment, offset: usize, data: &mut [u8]) -> Result<()> {
+        let present = segment.file_size.saturating_sub(offset).min(data.len());
+
+        read_file(&self.file, segment.offset + offset, &mut data[..present])?;
+
+        let address = segment.address + offset + present;
+        let missing = &mut data[present..];
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let mapping = self.mappings.iter()
+            .find(|mapping| mapping.verified && mapping.start <= address && address.saturating_add(missing.len()) <= mapping.end)
+            .ok_or_else(|| Error::InvalidOperation(format!("0x{:X} was left out of the core file", address)))?;
+
+        let file_offset = mapping.offset.checked_add(address - mapping.start).ok_or(Error::InvalidImage)?;
+
+        read_file(&File::open(&mapping.path)?, file_offset, missing)
+    }
+}
+
+impl ProcessTrait for Process {
+    fn attach(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn detach(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn mem_read(&self, address: usize, data: &mut [u8]) -> Result<()> {
+        let mut done = 0;
+
+        // a read may span neighbouring segments, anything outside of them wasn't mapped
+        while done < data.len() {
+            let current = address + done;
+
+            // segments don't overlap, so sorted by address they're sorted by their ends too
+            let index = self.segments.partition_point(|segment| segment.address + segment.size <= current);
+
+            let segment = match self.segments.get(index).filter(|segment| segment.address <= current) {
+                Some(segment) => segment,
+                None => return Err(Error::InvalidOperation(format!("0x{:X} is not mapped", current)))
+            };
+
+            let offset = current - segment.address;
+            let length = (segment.size - offset).min(data.len() - done);
+
+            self.read_segment(segment, offset, &mut data[done..done + length])?;
+
+            done += length;
+        }
+
+        Ok(())
+    }
+
+    fn mem_write(&self, _address: usize, _data: &[u8]) -> Result<()> {
+        Err(Error::InvalidOperation("core files are read only".to_string()))
+    }
+
+    fn mod_find(&self, name: &str) -> Result<ProcessModule> {
+        let found: Vec<&Mapping> = self.mappings.iter()
+            .filter(|mapping| {
+                let file_name = match Path::new(&mapping.path).file_name().and_then(|file_name| file_name.to_str()) {
+                    Some(file_name) => file_name.to_lowercase(),
+                    None => return false
+                };
+
+                match file_name.split_terminator('.').next() {
+                    Some(file_name) => Path::new(file_name) == Path::new(&name.to_lowercase()).with_extension(""),
+                    None => false
+                }
+            })
+            .collect();
+
+        match (found.first(), found.last()) {
+            (Some(first), Some(last)) => Ok(ProcessModule {
+                module_base: first.start,
+                module_size: last.end - first.start
+            }),
+            _ => Err(Error::NotFound)
+        }
+    }
+
+    fn mod_export(&self, module: &ProcessModule, name: &str) -> Result<usize> {
+        match self.mod_exports(module)?.into_iter().find(|export| export.name == name) {
+            Some(export) => Ok(export.address),
+            None => Err(Error::NotFound)
+        }
+    }
+
+    fn mod_exports(&self, module: &ProcessModule) -> Result<Vec<ProcessExport>> {
+        image_exports(self, module)
+    }
+}
+
+// NT_FILE: count and page size, then a (start, end, page offset) triple and a path for each mapping
+fn file_mappings(notes: &[u8]) -> Result<Vec<Mapping>> {
+    let mut mappings: Vec<Mapping> = Vec::new();
+
+    let mut offset: usize = 0;
+    while offset.checked_add(size_of::<Elf64_Nhdr>()).is_some_and(|end| end <= notes.len()) {
+        let nhdr: Elf64_Nhdr = image::read(notes, offset)?;
+
+        let desc = (offset + size_of::<Elf64_Nhdr>()).checked_add((nhdr.n_namesz as usize).next_multiple_of(4)).ok_or(Error::InvalidImage)?;
+        offset = desc.checked_add((nhdr.n_descsz as usize).next_multiple_of(4)).ok_or(Error::InvalidImage)?;
+
+        if nhdr.n_type != NT_FILE {
+            continue;
+        }
+
+        let desc = desc.checked_add(nhdr.n_descsz as usize)
+            .and_then(|end| notes.get(desc..end))
+            .ok_or(Error::InvalidImage)?;
+
+        let count: u64 = image::read(desc, 0)?;
+        let page_size: u64 = image::read(desc, 8)?;
+
+        // the triples have to fit in the note, which also bounds the count
+        let paths = (count as usize).checked_mul(24)
+            .and_then(|size| size.checked_add(16))
+            .and_then(|paths| desc.get(paths..))
+            .ok_or(Error::InvalidImage)?;
+
+        let mut paths = paths.split(|byte| *byte == 0);
+
+        for i in 0..count as usize {
+            let [start, end, page]: [u64; 3] = image::read(desc, 16 + i * 24)?;
+
+            let path = match paths.next() {
+                Some(path) => String::from_utf8_lossy(path).into_owned(),
+                None => return Err(Error::InvalidImage)
+            };
+
+            if start > end {
+                return Err(Error::InvalidImage);
+            }
+
+            mappings.push(Mapping {
+                start: start as usize,
+                end: end as usize,
+                offset: page.checked_mul(page_size).ok_or(Error::InvalidImage)? as usize,
+                path,
+                verified: false
+            });
+        }
+    }
+
+    Ok(mappings)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+    use crate::image::write;
+    use super::*;
+
+    const BASE: usize = 0x10000;
+    const PAGE_SIZE: usize = 0x1000;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("cs2-dumper-{}-{}", std::process::id(), name))
+    }
+
+    // an NT_FILE note mapping `path` at BASE over `pages` pages, with `count` as its mapping count
+    fn note(path: &str, count: u64, pages: usize) -> Vec<u8> {
+        let mut desc: Vec<u8> = Vec::new();
+        desc.extend_from_slice(&count.to_le_bytes());
+        desc.extend_from_slice(&(PAGE_SIZE as u64).to_le_bytes());
+
+        for value in [BASE, BASE + pages * PAGE_SIZE, 0] {
+            desc.extend_from_slice(&(value as u64).to_le_bytes());
+        }
+
+        desc.extend_from_slice(path.as_bytes());
+        desc.push(0);
+
+        let mut note = vec![0u8; size_of::<Elf64_Nhdr>()];
+        write(&mut note, 0, Elf64_Nhdr { n_namesz: 5, n_descsz: desc.len() as u32, n_type: NT_FILE }).unwrap();
+        note.extend_from_slice(b"CORE\0\0\0\0");
+        note.extend_from_slice(&desc);
+
+        note
+    }
+
+    // the first page of a mapped library, as the coredump filter keeps it
+    fn header(e_machine: u16) -> Vec<u8> {
+        let mut header = vec![0u8; PAGE_SIZE];
+
+        let mut ehdr: Elf64_Ehdr = image::read(&header, 0).unwrap();
+        ehdr.e_ident[0..4].copy_from_slice(&ELFMAGIC);
+        ehdr.e_machine = e_machine;
+        write(&mut header, 0, ehdr).unwrap();
+
+        header
+    }
+
+    // a core of a process that mapped `library` at BASE over two pages, keeping only its header page
+    fn core(library: &Path) -> Vec<u8> {
+        let note = note(library.to_str().unwrap(), 1, 2);
+
+        let mut data = vec![0u8; PAGE_SIZE];
+
+        let mut ehdr: Elf64_Ehdr = image::read(&data, 0).unwrap();
+        ehdr.e_ident[0..4].copy_from_slice(&ELFMAGIC);
+        ehdr.e_type = ET_CORE;
+        ehdr.e_phoff = size_of::<Elf64_Ehdr>() as u64;
+        ehdr.e_phentsize = size_of::<Elf64_Phdr>() as u16;
+        ehdr.e_phnum = 2;
+        write(&mut data, 0, ehdr).unwrap();
+
+        let notes = Elf64_Phdr { p_type: PT_NOTE, p_flags: 0, p_offset: 0x100, p_vaddr: 0, p_paddr: 0, p_filesz: note.len() as u64, p_memsz: 0, p_align: 4 };
+        let load = Elf64_Phdr { p_type: PT_LOAD, p_flags: 5, p_offset: PAGE_SIZE as u64, p_vaddr: BASE as u64, p_paddr: 0, p_filesz: PAGE_SIZE as u64, p_memsz: 2 * PAGE_SIZE as u64, p_align: PAGE_SIZE as u64 };
+        write(&mut data, 0x40, notes).unwrap();
+        write(&mut data, 0x40 + size_of::<Elf64_Phdr>(), load).unwrap();
+
+        data[0x100..0x100 + note.len()].copy_from_slice(&note);
+        data.extend_from_slice(&header(62));
+
+        data
+    }
+
+    fn open(name: &str, library: &[u8]) -> Result<Process> {
+        let library_path = temp_path(&format!("{}.so", name));
+        let core_path = temp_path(&format!("{}.core", name));
+
+        fs::write(&library_path, library).unwrap();
+        fs::write(&core_path, core(&library_path)).unwrap();
+
+        let process = Process::open(&core_path);
+
+        fs::remove_file(&core_path).unwrap();
+
+        process
+    }
+
+    #[test]
+    fn file_mappings_reads_nt_file() {
+        let mappings = file_mappings(&note("/usr/lib/libclient.so", 1, 2)).unwrap();
+
+        assert_eq!(mappings.len(), 1);
+        assert_eq!((mappings[0].start, mappings[0].end, mappings[0].offset), (BASE, BASE + 2 * PAGE_SIZE, 0));
+        assert_eq!(mappings[0].path, "/usr/lib/libclient.so");
+    }
+
+    #[test]
+    fn file_mappings_rejects_counts_past_the_note() {
+        for count in [2, u64::MAX / 8, u64::MAX] {
+            assert!(matches!(file_mappings(&note("/usr/lib/libclient.so", count, 2)), Err(Error::InvalidImage)), "{}", count);
+        }
+    }
+
+    #[test]
+    fn left_out_pages_are_read_from_the_same_host_file() {
+        let mut library = header(62);
+        library.extend_from_slice(&[0xAB; PAGE_SIZE]);
+
+        let process = open("same", &library).unwrap();
+
+        let mut data = [0u8; 0x20];
+        process.mem_read(BASE + PAGE_SIZE - 0x10, &mut data).unwrap();
+
+        assert_eq!(data[..0x10], [0; 0x10]);
+        assert_eq!(data[0x10..], [0xAB; 0x10]);
+
+        fs::remove_file(temp_path("same.so")).unwrap();
+    }
+
+    #[test]
+    fn left_out_pages_of_another_host_file_fail_to_read() {
+        let mut library = header(183);
+        library.extend_from_slice(&[0xAB; PAGE_SIZE]);
+
+        let process = open("other", &library).unwrap();
+
+        let mut data = [0u8; 0x10];
+        assert!(process.mem_read(BASE, &mut data).is_ok());
+        assert!(matches!(process.mem_read(BASE + PAGE_SIZE, &mut data), Err(Error::InvalidOperation(_))));
+
+        fs::remove_file(temp_path("other.so")).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+use std::{fs, path::Path};
+use crate::{error::{Error, Result}, output::model::Model};
+use super::{image_exports, ProcessExport, ProcessModule, ProcessTrait};
+
+struct Module {
+    name: String,
+    base: usize,
+    data: Vec<u8>
+}
+
+// module dumps mapped back at the bases they were taken from, for running without the game
+pub struct Process {
+    modules: Vec<Module>
+}
+
+impl Process {
+    // every module recorded in a manifest, or in the manifest of an output directory
+    pub fn from_manifest(path: &Path) -> Result<Self> {
+        let model = Model::load(path)?;
+
+        let root = match path.is_dir() {
+            true => path,
+            false => path.parent().unwrap_or(Path::new(""))
+        };
+
+        let mut process = Self { modules: Vec::new() };
+
+        for module in &model.modules {
+            let file = root.join("modules").join(Path::new(&module.name).with_extension("")).join(&module.file);
+
+            match process.map(&module.name, module.base, &file) {
+                Ok(_) => println!("mapped module: {} at 0x{:X}", module.name, module.base),
+                Err(err) => println!("failed to map module: {}, error: {}", file.display(), err)
+            }
+        }
+
+        match process.modules.is_empty() {
+            true => Err(Error::NotFound),
+            false => Ok(process)
+        }
+    }
+
+    pub fn map(&mut self, name: &str, base: usize, path: &Path) -> Result<()> {
+        let data = fs::read(path)?;
+
+        let end = base.checked_add(data.len())
+            .ok_or_else(|| Error::InvalidOperation(format!("0x{:X} is past the end of the address space", base)))?;
+
+        if self.modules.iter().any(|module| base < module.base + module.data.len() && module.base < end) {
+            return Err(Error::InvalidOperation(format!("0x{:X} overlaps a mapped module", base)));
+        }
+
+        self.modules.push(Module {
+            name: name.to_string(),
+            base,
+            data
+        });
+
+        Ok(())
+    }
+}
+
+impl ProcessTrait for Process {
+    fn attach(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn detach(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    // reads have to fall inside one module, everything between modules is unmapped
+    fn mem_read(&self, address: usize, data: &mut [u8]) -> Result<()> {
+        for module in &self.modules {
+            if address < module.base {
+                continue;
+            }
+
+            let offset = address - module.base;
+
+            let end = match offset.checked_add(data.len()) {
+                Some(end) => end,
+                None => return Err(Error::InvalidOperation(format!("0x{:X} is past the end of the address space", address)))
+            };
+
+            if let Some(bytes) = module.data.get(offset..end) {
+                data.copy_from_slice(bytes);
+                return Ok(());
+            }
+        }
+
+        Err(Error::InvalidOperation(format!("0x{:X} is not mapped", address)))
+    }
+
+    fn mem_write(&self, _address: usize, _data: &[u8]) -> Result<()> {
+        Err(Error::InvalidOperation("module dumps are read only".to_string()))
+    }
+
+    fn mod_find(&self, name: &str) -> Result<ProcessModule> {
+        let stem = |name: &str| Path::new(&name.to_lowercase()).with_extension("");
+
+        match self.modules.iter().find(|module| stem(&module.name) == stem(name)) {
+            Some(module) => Ok(ProcessModule {
+                module_base: module.base,
+                module_size: module.data.len()
+            }),
+            None => Err(Error::NotFound)
+        }
+    }
+
+    fn mod_export(&self, module: &ProcessModule, name: &str) -> Result<usize> {
+        match self.mod_exports(module)?.into_iter().find(|export| export.name == name) {
+            Some(export) => Ok(export.address),
+            None => Err(Error::NotFound)
+        }
+    }
+
+    // read back the same way as from the live process the dump was taken from
+    fn mod_exports(&self, module: &ProcessModule) -> Result<Vec<ProcessExport>> {
+        image_exports(self, module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::game::{modules::DumpedModule, offsets::Offsets};
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("cs2-dumper-{}-{}", std::process::id(), name))
+    }
+
+    fn process(modules: &[(&str, usize, usize)]) -> Process {
+        Process {
+            modules: modules.iter()
+                .map(|(name, base, size)| Module { name: name.to_string(), base: *base, data: vec![0xAA; *size] })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn mod_find_matches_the_file_stem() {
+        let process = process(&[("client.dll", 0x10000, 0x100), ("libengine2.so", 0x20000, 0x200)]);
+
+        for name in ["client.dll", "CLIENT.DLL", "client"] {
+            assert_eq!(process.mod_find(name).unwrap().module_base, 0x10000, "{}", name);
+        }
+
+        assert_eq!(process.mod_find("libengine2").unwrap().module_size, 0x200);
+
+        for name in ["libclient.so", "engine2.dll", "client.dll.bak"] {
+            assert!(matches!(process.mod_find(name), Err(Error::NotFound)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn mem_read_stays_inside_a_dump() {
+        let process = process(&[("client.dll", 0x10000, 0x100)]);
+
+        let mut data = [0u8; 0x10];
+        process.mem_read(0x100F0, &mut data).unwrap();
+        assert_eq!(data, [0xAA; 0x10]);
+
+        for address in [0x100F8, 0x10100, 0xFFF8, usize::MAX - 4] {
+            assert!(matches!(process.mem_read(address, &mut data), Err(Error::InvalidOperation(_))), "0x{:X}", address);
+        }
+    }
+
+    #[test]
+    fn from_manifest_skips_overlapping_modules() {
+        let root = temp_path("overlapping-modules");
+
+        let modules = [("client.dll", 0x10000, 0x2000), ("engine2.dll", 0x11000, 0x1000), ("tier0.dll", 0x12000, 0x1000)];
+
+        for (name, _, size) in modules {
+            let directory = root.join("modules").join(Path::new(name).with_extension(""));
+            fs::create_dir_all(&directory).unwrap();
+            fs::write(directory.join(name), vec![0u8; size]).unwrap();
+        }
+
+        let dumped = modules.iter()
+            .map(|(name, base, size)| DumpedModule { name: name.to_string(), base: *base, size: *size, file: name.to_string() })
+            .collect();
+
+        Model::new(dumped, Vec::new(), Vec::new(), Vec::new(), Offsets::new()).save(&root.join("manifest.json")).unwrap();
+
+        let process = Process::from_manifest(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        let process = process.unwrap();
+
+        assert_eq!(process.mod_find("client.dll").unwrap().module_base, 0x10000);
+        assert!(matches!(process.mod_find("engine2.dll"), Err(Error::NotFound)));
+        assert_eq!(process.mod_find("tier0.dll").unwrap().module_base, 0x12000);
+    }
+}
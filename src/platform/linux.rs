@@ -1,9 +1,9 @@
-#![allow(dead_code, non_camel_case_types)]
+#![allow(dead_code)]
 #![cfg(target_os = "linux")]
 
-use std::{ffi::c_uchar, fs::{self, File}, os::unix::fs::FileExt, path::Path};
-use crate::error::Error;
-use super::{ProcessModule, ProcessTrait};
+use std::{fs::{self, File}, os::unix::fs::FileExt, path::Path};
+use crate::{error::Error, image::elf::DynamicSymbols};
+use super::{ProcessExport, ProcessModule, ProcessTrait};
 
 pub struct Process {
     process_id: u32,
@@ -29,7 +29,7 @@ impl Process {
                 None => continue
             };
 
-            let process_id = match u32::from_str_radix(process_id, 10) {
+            let process_id = match process_id.parse::<u32>() {
                 Ok(process_id) => process_id,
                 Err(_) => continue
             };
@@ -44,7 +44,7 @@ impl Process {
                 .unwrap_or(cmdline.len())];
 
             let file_path = match std::str::from_utf8(unsafe {
-                std::slice::from_raw_parts::<u8>(file_path.as_ptr() as *const u8, file_path.len())
+                std::slice::from_raw_parts::<u8>(file_path.as_ptr(), file_path.len())
             }) {
                 Ok(path) => path,
                 Err(_) => continue
@@ -150,51 +150,12 @@ impl ProcessTrait for Process {
             module_size: found.last().unwrap().0.1 - found.first().unwrap().0.0
         })
     }
-}
 
-pub const ELFMAG0: u8 = 0x7f;
-pub const ELFMAG1: u8 = 0x45;
-pub const ELFMAG2: u8 = 0x4c;
-pub const ELFMAG3: u8 = 0x46;
-pub const ELFMAGIC: [u8; 4] = [ELFMAG0, ELFMAG1, ELFMAG2, ELFMAG3];
-
-pub type Elf64_Half = u16;
-pub type Elf64_Addr = u64;
-pub type Elf64_Off = u64;
-pub type Elf64_Word = u32;
-pub type Elf64_Xword = u64;
-
-#[repr(C)]
-#[derive(Debug)]
-pub struct Elf64_Ehdr {
-
-    pub e_ident: [c_uchar; 16],
-    pub e_type: Elf64_Half,
-    pub e_machine: Elf64_Half,
-    pub e_version: Elf64_Word,
-    pub e_entry: Elf64_Addr,
-    pub e_phoff: Elf64_Off,
-    pub e_shoff: Elf64_Off,
-    pub e_flags: Elf64_Word,
-    pub e_ehsize: Elf64_Half,
-    pub e_phentsize: Elf64_Half,
-    pub e_phnum: Elf64_Half,
-    pub e_shentsize: Elf64_Half,
-    pub e_shnum: Elf64_Half,
-    pub e_shstrndx: Elf64_Half,
-}
+    fn mod_export(&self, module: &ProcessModule, name: &str) -> Result<usize, Error> {
+        DynamicSymbols::read(self, module)?.find(self, name)
+    }
 
-#[repr(C)]
-#[derive(Debug)]
-pub struct Elf64_Shdr {
-    pub sh_name: Elf64_Word,
-    pub sh_type: Elf64_Word,
-    pub sh_flags: Elf64_Xword,
-    pub sh_addr: Elf64_Addr,
-    pub sh_offset: Elf64_Off,
-    pub sh_size: Elf64_Xword,
-    pub sh_link: Elf64_Word,
-    pub sh_info: Elf64_Word,
-    pub sh_addralign: Elf64_Xword,
-    pub sh_entsize: Elf64_Xword,
+    fn mod_exports(&self, module: &ProcessModule) -> Result<Vec<ProcessExport>, Error> {
+        DynamicSymbols::read(self, module)?.exports(self)
+    }
 }
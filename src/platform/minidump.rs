@@ -0,0 +1,357 @@
+use std::{fs::File, mem::size_of, path::Path};
+use crate::{error::{Error, Result}, image::FromBytes};
+use super::{image_exports, read_file, read_struct, ProcessExport, ProcessModule, ProcessTrait};
+
+const MINIDUMP_SIGNATURE: u32 = 0x504d444d;
+
+const MODULE_LIST_STREAM: u32 = 4;
+const MEMORY_LIST_STREAM: u32 = 5;
+const MEMORY64_LIST_STREAM: u32 = 9;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct MinidumpHeader {
+    signature: u32,
+    version: u32,
+    number_of_streams: u32,
+    stream_directory_rva: u32,
+    check_sum: u32,
+    time_date_stamp: u32,
+    flags: u64
+}
+
+unsafe impl FromBytes for MinidumpHeader {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct MinidumpDirectory {
+    stream_type: u32,
+    data_size: u32,
+    rva: u32
+}
+
+unsafe impl FromBytes for MinidumpDirectory {}
+
+// MINIDUMP_MODULE is packed to 4 bytes, so it's 108 bytes long rather than 112
+#[repr(C, packed(4))]
+#[derive(Debug, Clone, Copy)]
+struct MinidumpModule {
+    base_of_image: u64,
+    size_of_image: u32,
+    check_sum: u32,
+    time_date_stamp: u32,
+    module_name_rva: u32,
+    version_info: [u32; 13],
+    cv_record: [u32; 2],
+    misc_record: [u32; 2],
+    reserved: [u64; 2]
+}
+
+unsafe impl FromBytes for MinidumpModule {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct MinidumpMemoryDescriptor {
+    start_of_memory_range: u64,
+    data_size: u32,
+    rva: u32
+}
+
+unsafe impl FromBytes for MinidumpMemoryDescriptor {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct MinidumpMemoryDescriptor64 {
+    start_of_memory_range: u64,
+    data_size: u64
+}
+
+unsafe impl FromBytes for MinidumpMemoryDescriptor64 {}
+
+struct Range {
+    address: usize,
+    size: usize,
+    offset: usize
+}
+
+struct Module {
+    name: String,
+    base: usize,
+    size: usize
+}
+
+// memory and modules of a Windows minidump, read the same way on any host
+pub struct Process {
+    file: File,
+    ranges: Vec<Range>,
+    modules: Vec<Module>
+}
+
+impl Process {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let file_size = file.metadata()?.len() as usize;
+
+        let header: MinidumpHeader = read_struct(&file, 0)?;
+
+        if header.signature != MINIDUMP_SIGNATURE {
+            return Err(Error::InvalidImage);
+        }
+
+        let mut ranges: Vec<Range> = Vec::new();
+        let mut modules: Vec<Module> = Vec::new();
+
+        let directories = table(header.stream_directory_rva as usize, header.number_of_streams as u64, size_of::<MinidumpDirectory>(), file_size)?;
+
+        for i in 0..header.number_of_streams as usize {
+            let directory: MinidumpDirectory = read_struct(&file, directories + i * size_of::<MinidumpDirectory>())?;
+
+            let stream = directory.rva as usize;
+
+            match directory.stream_type {
+                MODULE_LIST_STREAM => {
+                    let count: u32 = read_struct(&file, stream)?;
+                    let entries = table(stream + size_of::<u32>(), count as u64, size_of::<MinidumpModule>(), file_size)?;
+
+                    for i in 0..count as usize {
+                        let module: MinidumpModule = read_struct(&file, entries + i * size_of::<MinidumpModule>())?;
+
+                        modules.push(Module {
+                            name: read_string(&file, module.module_name_rva as usize, file_size)?,
+                            base: module.base_of_image as usize,
+                            size: module.size_of_image as usize
+                        });
+                    }
+                },
+                // only full memory dumps have a Memory64List, smaller ones list each range with its own rva
+                MEMORY_LIST_STREAM => {
+                    let count: u32 = read_struct(&file, stream)?;
+                    let entries = table(stream + size_of::<u32>(), count as u64, size_of::<MinidumpMemoryDescriptor>(), file_size)?;
+
+                    for i in 0..count as usize {
+                        let descriptor: MinidumpMemoryDescriptor = read_struct(&file, entries + i * size_of::<MinidumpMemoryDescriptor>())?;
+
+                        ranges.push(Range::new(descriptor.start_of_memory_range, descriptor.data_size as u64, descriptor.rva as u64, file_size)?);
+                    }
+                },
+                // the ranges' data follows each other starting at one base rva
+                MEMORY64_LIST_STREAM => {
+                    let [count, base_rva]: [u64; 2] = read_struct(&file, stream)?;
+                    let entries = table(stream + 2 * size_of::<u64>(), count, size_of::<MinidumpMemoryDescriptor64>(), file_size)?;
+
+                    let mut offset = base_rva;
+
+                    for i in 0..count as usize {
+                        let descriptor: MinidumpMemoryDescriptor64 = read_struct(&file, entries + i * size_of::<MinidumpMemoryDescriptor64>())?;
+
+                        let range = Range::new(descriptor.start_of_memory_range, descriptor.data_size, offset, file_size)?;
+                        offset += descriptor.data_size;
+
+                        ranges.push(range);
+                    }
+                },
+                _ => ()
+            }
+        }
+
+        ranges.sort_by_key(|range| range.address);
+
+        Ok(Self {
+            file,
+            ranges,
+            modules
+        })
+    }
+}
+
+impl Range {
+    // the range's data has to be in the file, checked before anything is read from it
+    fn new(address: u64, size: u64, offset: u64, file_size: usize) -> Result<Self> {
+        let in_file = offset.checked_add(size).is_some_and(|end| end <= file_size as u64);
+
+        match in_file && address.checked_add(size).is_some() {
+            true => Ok(Self {
+                address: address as usize,
+                size: size as usize,
+                offset: offset as usize
+            }),
+            false => Err(Error::InvalidImage)
+        }
+    }
+}
+
+impl ProcessTrait for Process {
+    fn attach(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn detach(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn mem_read(&self, address: usize, data: &mut [u8]) -> Result<()> {
+        let mut done = 0;
+
+        // a read may span neighbouring ranges, anything outside of them wasn't captured
+        while done < data.len() {
+            let current = address + done;
+
+            // ranges don't overlap, so sorted by address they're sorted by their ends too
+            let index = self.ranges.partition_point(|range| range.address + range.size <= current);
+
+            let range = match self.ranges.get(index).filter(|range| range.address <= current) {
+                Some(range) => range,
+                None => return Err(Error::InvalidOperation(format!("0x{:X} is not in the minidump", current)))
+            };
+
+            let offset = current - range.address;
+            let length = (range.size - offset).min(data.len() - done);
+
+            read_file(&self.file, range.offset + offset, &mut data[done..done + length])?;
+
+            done += length;
+        }
+
+        Ok(())
+    }
+
+    fn mem_write(&self, _address: usize, _data: &[u8]) -> Result<()> {
+        Err(Error::InvalidOperation("minidumps are read only".to_string()))
+    }
+
+    // module names are full Windows paths, which Path won't split on other hosts
+    fn mod_find(&self, name: &str) -> Result<ProcessModule> {
+        let stem = |name: &str| Path::new(&name.to_lowercase()).with_extension("");
+
+        let module = self.modules.iter().find(|module| {
+            let file_name = module.name.rsplit(['\\', '/']).next().unwrap_or(&module.name);
+            stem(file_name) == stem(name)
+        });
+
+        match module {
+            Some(module) => Ok(ProcessModule {
+                module_base: module.base,
+                module_size: module.size
+            }),
+            None => Err(Error::NotFound)
+        }
+    }
+
+    fn mod_export(&self, module: &ProcessModule, name: &str) -> Result<usize> {
+        match self.mod_exports(module)?.into_iter().find(|export| export.name == name) {
+            Some(export) => Ok(export.address),
+            None => Err(Error::NotFound)
+        }
+    }
+
+    fn mod_exports(&self, module: &ProcessModule) -> Result<Vec<ProcessExport>> {
+        image_exports(self, module)
+    }
+}
+
+// where a table of `count` entries starting at `offset` ends, if it ends inside the file
+fn table(offset: usize, count: u64, entry: usize, file_size: usize) -> Result<usize> {
+    let end = (count as usize).checked_mul(entry).and_then(|size| size.checked_add(offset));
+
+    match end {
+        Some(end) if end <= file_size => Ok(offset),
+        _ => Err(Error::InvalidImage)
+    }
+}
+
+// MINIDUMP_STRING: a byte length followed by UTF-16
+fn read_string(file: &File, offset: usize, file_size: usize) -> Result<String> {
+    let length: u32 = read_struct(file, offset)?;
+
+    table(offset + size_of::<u32>(), length as u64, 1, file_size)?;
+
+    let mut data = vec![0u8; length as usize];
+    read_file(file, offset + size_of::<u32>(), &mut data)?;
+
+    let units: Vec<u16> = data.chunks_exact(2).map(|unit| u16::from_le_bytes([unit[0], unit[1]])).collect();
+
+    Ok(String::from_utf16_lossy(&units))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::*;
+
+    // a full memory minidump holding `ranges`, in the order given, filled with their fill byte
+    fn minidump(count: u64, ranges: &[(u64, u8)]) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+
+        for value in [MINIDUMP_SIGNATURE, 0xA793, 1, 0x20] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        data.resize(0x20, 0);
+
+        for value in [MEMORY64_LIST_STREAM, 0x10 + ranges.len() as u32 * 0x10, 0x40] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        data.resize(0x40, 0);
+
+        for value in [count, 0x100] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        for (address, _) in ranges {
+            data.extend_from_slice(&address.to_le_bytes());
+            data.extend_from_slice(&0x10u64.to_le_bytes());
+        }
+
+        data.resize(0x100, 0);
+
+        for (_, fill) in ranges {
+            data.extend_from_slice(&[*fill; 0x10]);
+        }
+
+        data
+    }
+
+    fn open(name: &str, data: &[u8]) -> Result<Process> {
+        let path = std::env::temp_dir().join(format!("cs2-dumper-{}-{}.dmp", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+
+        let process = Process::open(&path);
+
+        fs::remove_file(&path).unwrap();
+
+        process
+    }
+
+    #[test]
+    fn mem_read_spans_neighbouring_ranges() {
+        let process = open("ranges", &minidump(3, &[(0x2000, 0xCC), (0x1000, 0x11), (0x1010, 0x22)])).unwrap();
+
+        let mut data = [0u8; 0x10];
+
+        process.mem_read(0x1008, &mut data).unwrap();
+        assert_eq!(data, [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22]);
+
+        process.mem_read(0x2000, &mut data).unwrap();
+        assert_eq!(data, [0xCC; 0x10]);
+
+        for address in [0xFF0, 0x1018, 0x2008] {
+            assert!(matches!(process.mem_read(address, &mut data), Err(Error::InvalidOperation(_))), "0x{:X}", address);
+        }
+    }
+
+    #[test]
+    fn open_rejects_tables_past_the_file() {
+        for count in [0x100, u64::MAX / 0x10, u64::MAX] {
+            assert!(matches!(open(&format!("count-{}", count), &minidump(count, &[(0x1000, 0x11)])), Err(Error::InvalidImage)), "{}", count);
+        }
+    }
+
+    #[test]
+    fn ranges_have_to_be_in_the_file() {
+        assert!(Range::new(0x1000, 0x10, 0xF0, 0x100).is_ok());
+        assert!(matches!(Range::new(0x1000, 0x10, 0xF8, 0x100), Err(Error::InvalidImage)));
+        assert!(matches!(Range::new(u64::MAX, 0x10, 0, 0x100), Err(Error::InvalidImage)));
+        assert!(matches!(Range::new(0x1000, u64::MAX, 0x10, 0x100), Err(Error::InvalidImage)));
+    }
+}
@@ -1,6 +1,10 @@
-use crate::error::Result;
+use std::{fs::File, io::{Read, Seek, SeekFrom}, mem::{size_of, MaybeUninit}};
+use crate::{error::Result, image::{self, elf::DynamicSymbols, pe, FromBytes, ImageFormat, ModuleImage}};
 
+pub mod coredump;
+pub mod file;
 pub mod linux;
+pub mod minidump;
 pub mod windows;
 
 #[cfg(target_os = "windows")]
@@ -8,8 +12,8 @@ pub type Process = windows::Process;
 #[cfg(target_os = "linux")]
 pub type Process = linux::Process;
 
-#[cfg(target_os = "windows")]
-pub const DEFAULT_MODULES: [&'static str; 20] = [
+// dumps can come from the other platform than the host, so both module lists are always around
+pub const WINDOWS_MODULES: [&str; 20] = [
     "client.dll",
     "engine2.dll",
     "schemasystem.dll",
@@ -32,8 +36,7 @@ pub const DEFAULT_MODULES: [&'static str; 20] = [
     "server.dll"
 ];
 
-#[cfg(target_os = "linux")]
-pub const DEFAULT_MODULES: [&'static str; 20] = [
+pub const LINUX_MODULES: [&str; 20] = [
     "libclient.so",
     "libengine2.so",
     "libschemasystem.so",
@@ -56,6 +59,11 @@ pub const DEFAULT_MODULES: [&'static str; 20] = [
     "libserver.so"
 ];
 
+#[cfg(target_os = "windows")]
+pub const DEFAULT_MODULES: [&str; 20] = WINDOWS_MODULES;
+#[cfg(target_os = "linux")]
+pub const DEFAULT_MODULES: [&str; 20] = LINUX_MODULES;
+
 #[allow(dead_code)]
 pub trait ProcessTrait {
     fn attach(&mut self) -> Result<()>;
@@ -65,9 +73,151 @@ pub trait ProcessTrait {
     fn mem_write(&self, address: usize, data: &[u8]) -> Result<()>;
 
     fn mod_find(&self, name: &str) -> Result<ProcessModule>;
+    fn mod_export(&self, module: &ProcessModule, name: &str) -> Result<usize>;
+    fn mod_exports(&self, module: &ProcessModule) -> Result<Vec<ProcessExport>>;
+
+    fn read<T: FromBytes>(&self, address: usize) -> Result<T> {
+        let mut value = MaybeUninit::<T>::zeroed();
+        self.mem_read(address, unsafe {
+            std::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>())
+        })?;
+        Ok(unsafe { value.assume_init() })
+    }
+
+    fn read_string(&self, address: usize) -> Result<String> {
+        let mut data: Vec<u8> = Vec::new();
+
+        // read up to page boundaries so we never cross into an unmapped page
+        let mut address = address;
+        while data.len() < 0x1000 {
+            let mut chunk = [0u8; 0x40];
+            let chunk_size = chunk.len().min(0x1000 - (address & 0xFFF));
+
+            self.mem_read(address, &mut chunk[..chunk_size])?;
+
+            if let Some(end) = chunk[..chunk_size].iter().position(|c| *c == 0) {
+                data.extend_from_slice(&chunk[..end]);
+                break;
+            }
+
+            data.extend_from_slice(&chunk[..chunk_size]);
+            address += chunk_size;
+        }
+
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
 }
 
 pub struct ProcessModule {
     pub module_base: usize,
     pub module_size: usize
 }
+
+pub struct ProcessExport {
+    pub name: String,
+    pub address: usize
+}
+
+// exports read back from the mapped image, for processes without a loader to ask
+fn image_exports(process: &impl ProcessTrait, module: &ProcessModule) -> Result<Vec<ProcessExport>> {
+    let mut magic = [0u8; 4];
+    process.mem_read(module.module_base, &mut magic)?;
+
+    match ImageFormat::detect(&magic)? {
+        // forwarded exports have no code in this module, so they can't be resolved to an address here
+        ImageFormat::Pe => Ok(pe::exports(&ModuleImage { process, module })?.into_iter()
+            .filter(|export| export.forwarder.is_none())
+            .filter_map(|export| Some(ProcessExport {
+                name: export.name?,
+                address: module.module_base + export.rva
+            }))
+            .collect()),
+        ImageFormat::Elf => DynamicSymbols::read(process, module)?.exports(process)
+    }
+}
+
+// reads from dump files, which can be far too large to load whole
+fn read_file(mut file: &File, offset: usize, data: &mut [u8]) -> Result<()> {
+    file.seek(SeekFrom::Start(offset as u64))?;
+    file.read_exact(data)?;
+    Ok(())
+}
+
+fn read_struct<T: FromBytes>(file: &File, offset: usize) -> Result<T> {
+    let mut data = vec![0u8; size_of::<T>()];
+    read_file(file, offset, &mut data)?;
+    image::read(&data, 0)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::ops::Range;
+    use crate::error::{Error, Result};
+    use super::{ProcessExport, ProcessModule, ProcessTrait};
+
+    // a flat buffer at a fixed address, holes fail to read like unmapped pages
+    pub struct Memory {
+        pub base: usize,
+        pub data: Vec<u8>,
+        pub holes: Vec<Range<usize>>,
+        pub exports: Vec<(&'static str, usize)>
+    }
+
+    impl Memory {
+        pub fn new(base: usize, data: Vec<u8>) -> Self {
+            Self { base, data, holes: Vec::new(), exports: Vec::new() }
+        }
+
+        pub fn module(&self) -> ProcessModule {
+            ProcessModule {
+                module_base: self.base,
+                module_size: self.data.len()
+            }
+        }
+    }
+
+    impl ProcessTrait for Memory {
+        fn attach(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn detach(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn mem_read(&self, address: usize, data: &mut [u8]) -> Result<()> {
+            let end = address + data.len();
+
+            if self.holes.iter().any(|hole| address < hole.end && hole.start < end) {
+                return Err(Error::InvalidOperation(format!("0x{:X} is not mapped", address)));
+            }
+
+            match address.checked_sub(self.base).and_then(|offset| self.data.get(offset..offset + data.len())) {
+                Some(bytes) => {
+                    data.copy_from_slice(bytes);
+                    Ok(())
+                },
+                None => Err(Error::InvalidOperation(format!("0x{:X} is not mapped", address)))
+            }
+        }
+
+        fn mem_write(&self, _address: usize, _data: &[u8]) -> Result<()> {
+            Err(Error::InvalidOperation("test memory is read only".to_string()))
+        }
+
+        fn mod_find(&self, _name: &str) -> Result<ProcessModule> {
+            Ok(self.module())
+        }
+
+        fn mod_export(&self, _module: &ProcessModule, name: &str) -> Result<usize> {
+            match self.exports.iter().find(|export| export.0 == name) {
+                Some((_, address)) => Ok(*address),
+                None => Err(Error::NotFound)
+            }
+        }
+
+        fn mod_exports(&self, _module: &ProcessModule) -> Result<Vec<ProcessExport>> {
+            Ok(Vec::new())
+        }
+    }
+}
@@ -24,8 +24,8 @@ use windows::Win32::{
     }
 };
 
-use crate::error::Error;
-use super::{ProcessModule, ProcessTrait};
+use crate::{error::Error, image::{pe, ModuleImage}};
+use super::{ProcessExport, ProcessModule, ProcessTrait};
 
 #[derive(Clone)]
 pub struct Process {
@@ -48,8 +48,10 @@ impl Process {
             Err(error) => return Err(Error::WindowsError(error))
         };
     
-        let mut entry = PROCESSENTRY32::default();
-        entry.dwSize = std::mem::size_of::<PROCESSENTRY32>() as u32;
+        let mut entry = PROCESSENTRY32 {
+            dwSize: std::mem::size_of::<PROCESSENTRY32>() as u32,
+            ..Default::default()
+        };
     
         if let Err(error) = unsafe { Process32First(snapshot, &mut entry) } {
             return Err(Error::WindowsError(error));
@@ -101,10 +103,12 @@ impl ProcessTrait for Process {
             return Err(Error::AlreadyAttached)
         }
 
-        Ok(match unsafe { OpenProcess(PROCESS_ALL_ACCESS, false, self.process_id) } {
+        match unsafe { OpenProcess(PROCESS_ALL_ACCESS, false, self.process_id) } {
             Ok(handle) => self.process_handle = Some(handle),
             Err(error) => return Err(Error::WindowsError(error))
-        })
+        }
+
+        Ok(())
     }
 
     fn detach(&mut self) -> Result<(), Error> {
@@ -113,10 +117,12 @@ impl ProcessTrait for Process {
             None => return Err(Error::NotAttached)
         };
 
-        Ok(match unsafe { CloseHandle(handle) } {
+        match unsafe { CloseHandle(handle) } {
             Ok(_) => self.process_handle = None,
             Err(error) => return Err(Error::WindowsError(error))
-        })
+        }
+
+        Ok(())
     }
 
     fn mem_read(&self, address: usize, data: &mut [u8]) -> Result<(), Error> {
@@ -125,7 +131,7 @@ impl ProcessTrait for Process {
             None => return Err(Error::NotAttached)
         };
 
-        Ok(if let Err(error) = unsafe { ReadProcessMemory(
+        if let Err(error) = unsafe { ReadProcessMemory(
             handle,
             address as _,
             data.as_mut_ptr() as *mut ffi::c_void,
@@ -133,7 +139,9 @@ impl ProcessTrait for Process {
             None) } {
 
             return Err(Error::WindowsError(error));
-        })
+        }
+
+        Ok(())
     }
 
     fn mem_write(&self, address: usize, data: &[u8]) -> Result<(), Error> {
@@ -142,7 +150,7 @@ impl ProcessTrait for Process {
             None => return Err(Error::NotAttached)
         };
         
-        Ok(if let Err(error) = unsafe { WriteProcessMemory(
+        if let Err(error) = unsafe { WriteProcessMemory(
             handle,
             address as _,
             data.as_ptr() as _,
@@ -150,7 +158,9 @@ impl ProcessTrait for Process {
             None) } {
 
             return Err(Error::WindowsError(error));
-        })
+        }
+
+        Ok(())
     }
 
     fn mod_find(&self, name: &str) -> Result<ProcessModule, Error> {
@@ -160,8 +170,10 @@ impl ProcessTrait for Process {
             Err(error) => return Err(Error::WindowsError(error))
         };
     
-        let mut entry = MODULEENTRY32::default();
-        entry.dwSize = std::mem::size_of::<MODULEENTRY32>() as u32;
+        let mut entry = MODULEENTRY32 {
+            dwSize: std::mem::size_of::<MODULEENTRY32>() as u32,
+            ..Default::default()
+        };
     
         if let Err(error) = unsafe { Module32First(snapshot, &mut entry) } {
             return Err(Error::WindowsError(error));
@@ -219,4 +231,24 @@ impl ProcessTrait for Process {
             None => Err(Error::NotFound)
         }
     }
+
+    fn mod_export(&self, module: &ProcessModule, name: &str) -> Result<usize, Error> {
+
+        match self.mod_exports(module)?.into_iter().find(|export| export.name == name) {
+            Some(export) => Ok(export.address),
+            None => Err(Error::NotFound)
+        }
+    }
+
+    fn mod_exports(&self, module: &ProcessModule) -> Result<Vec<ProcessExport>, Error> {
+
+        // forwarded exports have no code in this module, so they can't be resolved to an address here
+        Ok(pe::exports(&ModuleImage { process: self, module })?.into_iter()
+            .filter(|export| export.forwarder.is_none())
+            .filter_map(|export| Some(ProcessExport {
+                name: export.name?,
+                address: module.module_base + export.rva
+            }))
+            .collect())
+    }
 }
@@ -0,0 +1,534 @@
+use std::{cell::OnceCell, collections::HashMap, fmt, fs};
+use iced_x86::{Decoder, DecoderOptions, Instruction};
+use crate::{error::{Error, Result}, game::modules::find_dump, image::{self, pe, ImageFormat, Section}, pattern::Pattern};
+use super::{generate, print, relocatable, Generated};
+
+// bytes of the old site compared against candidates in the new build
+const WINDOW_LENGTH: usize = 0x20;
+
+// bytes from the start of a function that identify it
+const FINGERPRINT_LENGTH: usize = 0x40;
+
+// how far from where an anchor predicts it the site is looked for
+const SEARCH_DISTANCE: usize = 0x200;
+
+// how far back a function start is looked for when the image doesn't record functions
+const MAX_FUNCTION_DISTANCE: usize = 0x4000;
+
+const MIN_SIMILARITY: f32 = 0.6;
+const MIN_STRING_LENGTH: usize = 4;
+const MAX_STRING_LENGTH: usize = 0x400;
+
+const ENDBR64: [u8; 4] = [0xF3, 0x0F, 0x1E, 0xFA];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Function,
+    Bytes,
+    String
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::Function => write!(f, "function fingerprint"),
+            Method::Bytes => write!(f, "byte pattern"),
+            Method::String => write!(f, "string xref")
+        }
+    }
+}
+
+pub struct Migration {
+    pub old_site: usize,
+    pub new_site: usize,
+    pub method: Method,
+    pub similarity: f32,
+    // for a migrated data address, where the rip relative operand at the new site points
+    pub resolved: Option<usize>,
+    pub generated: Generated
+}
+
+// finds where an address of the old dump went in the new one and prints a signature for it
+pub fn run(old: &str, rva: usize, new: &str) {
+    let mut dumps = Vec::new();
+
+    for module in [old, new] {
+        match find_dump(module).and_then(|path| Ok(fs::read(path)?)) {
+            Ok(data) => dumps.push(data),
+            Err(err) => {
+                println!("failed to read a dump of module: {}, error: {}", module, err);
+                return;
+            }
+        }
+    }
+
+    let migration = match migrate(&dumps[0], rva, &dumps[1]) {
+        Ok(migration) => migration,
+        Err(err) => {
+            println!("failed to migrate 0x{:X}, error: {}", rva, err);
+            return;
+        }
+    };
+
+    println!(
+        "migrated 0x{:X} -> 0x{:X} by {} ({:.0}% similar)",
+        migration.old_site, migration.new_site, migration.method, migration.similarity * 100.0
+    );
+
+    if let Some(resolved) = migration.resolved {
+        println!("new offset: 0x{:X}", resolved);
+    }
+
+    print(new, migration.generated);
+}
+
+// code addresses are looked for directly, data addresses through the first instruction referencing them
+pub fn migrate(old: &[u8], rva: usize, new: &[u8]) -> Result<Migration> {
+    let old_image = Image::new(old)?;
+    let new_image = Image::new(new)?;
+
+    let (site, data_address) = match old_image.is_code(rva) {
+        true => (rva, false),
+        false => match old_image.references().get(&rva).and_then(|sites| sites.iter().min().copied()) {
+            Some(site) => (site, true),
+            None => return Err(Error::InvalidOperation(format!("nothing in the old code references 0x{:X}", rva)))
+        }
+    };
+
+    let window = old_image.window(site, WINDOW_LENGTH).ok_or(Error::InvalidImage)?;
+
+    let found = find_function(&old_image, &new_image, site, &window)
+        .or_else(|| find_bytes(&new_image, &window))
+        .or_else(|| find_strings(&old_image, &new_image, site, &window));
+
+    let (new_site, method, similarity) = match found {
+        Some(found) => found,
+        None => return Err(Error::InvalidOperation(format!("no match for 0x{:X} in the new dump", site)))
+    };
+
+    let resolved = match data_address {
+        true => new_image.rip_target(new_site),
+        false => None
+    };
+
+    Ok(Migration {
+        old_site: site,
+        new_site,
+        method,
+        similarity,
+        resolved,
+        generated: generate(new, new_site)?
+    })
+}
+
+// the start of the surrounding function is unique in the new build, the site is near the same distance into it
+fn find_function(old: &Image, new: &Image, site: usize, window: &Pattern) -> Option<(usize, Method, f32)> {
+    let start = old.function_start(site)?;
+    let fingerprint = old.window(start, FINGERPRINT_LENGTH)?;
+
+    match fingerprint.scan(new.data)[..] {
+        [new_start] => new.refine(window, new_start + (site - start)).map(|(site, similarity)| (site, Method::Function, similarity)),
+        _ => None
+    }
+}
+
+// the bytes around the site didn't change beyond relocations
+fn find_bytes(new: &Image, window: &Pattern) -> Option<(usize, Method, f32)> {
+    match window.scan(new.data)[..] {
+        [site] => Some((site, Method::Bytes, 1.0)),
+        _ => None
+    }
+}
+
+// strings the surrounding code references are found again in the new build, along with the code referencing them
+fn find_strings(old: &Image, new: &Image, site: usize, window: &Pattern) -> Option<(usize, Method, f32)> {
+    let start = old.function_start(site).unwrap_or(site.saturating_sub(SEARCH_DISTANCE));
+    let strings = old.referenced_strings(start, site.saturating_add(SEARCH_DISTANCE));
+
+    if strings.is_empty() {
+        return None;
+    }
+
+    let references = new.references();
+
+    let mut best: Option<(usize, f32)> = None;
+
+    for (old_reference, string) in strings {
+        let mut needle = string.clone();
+        needle.push(0);
+
+        let occurrences = new.data.windows(needle.len())
+            .enumerate()
+            .filter(|(offset, bytes)| *bytes == needle.as_slice() && (*offset == 0 || new.data[offset - 1] == 0))
+            .map(|(offset, _)| offset);
+
+        for occurrence in occurrences {
+            for new_reference in references.get(&occurrence).into_iter().flatten() {
+                let predicted = match new_reference.checked_add(site).and_then(|predicted| predicted.checked_sub(old_reference)) {
+                    Some(predicted) => predicted,
+                    None => continue
+                };
+
+                if let Some((candidate, similarity)) = new.refine(window, predicted) {
+                    if best.is_none_or(|(_, best)| similarity > best) {
+                        best = Some((candidate, similarity));
+                    }
+                }
+            }
+        }
+    }
+
+    best.map(|(site, similarity)| (site, Method::String, similarity))
+}
+
+struct Image<'a> {
+    data: &'a [u8],
+    code: Vec<Section>,
+    format: ImageFormat,
+    // computed the first time it's needed, decoding all code is the slow part of a migration
+    references: OnceCell<HashMap<usize, Vec<usize>>>
+}
+
+impl<'a> Image<'a> {
+    fn new(data: &'a [u8]) -> Result<Self> {
+        Ok(Self {
+            data,
+            code: image::sections(data)?.into_iter().filter(|section| section.executable).collect(),
+            format: ImageFormat::detect(data)?,
+            references: OnceCell::new()
+        })
+    }
+
+    fn is_code(&self, rva: usize) -> bool {
+        self.code.iter().any(|section| (section.rva..section.rva.saturating_add(section.size)).contains(&rva))
+    }
+
+    fn decoder(&self, rva: usize, end: usize) -> Option<Decoder<'a>> {
+        let code = self.data.get(rva..end.min(self.data.len()))?;
+        Some(Decoder::with_ip(64, code, rva as u64, DecoderOptions::NONE))
+    }
+
+    // whole instructions from `rva` covering at least `length` bytes, relocatable operands wildcarded
+    fn window(&self, rva: usize, length: usize) -> Option<Pattern> {
+        let mut decoder = self.decoder(rva, rva.saturating_add(length + 0x10))?;
+        let mut instruction = Instruction::default();
+
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut mask: Vec<u8> = Vec::new();
+
+        while bytes.len() < length && decoder.can_decode() {
+            decoder.decode_out(&mut instruction);
+
+            if instruction.is_invalid() {
+                break;
+            }
+
+            let offsets = decoder.get_constant_offsets(&instruction);
+
+            let start = bytes.len();
+            bytes.extend_from_slice(&self.data[rva + start..rva + start + instruction.len()]);
+            mask.resize(bytes.len(), 0xFF);
+
+            for (offset, size) in relocatable(&instruction, &offsets) {
+                mask[start + offset..start + offset + size].fill(0);
+            }
+        }
+
+        match bytes.is_empty() {
+            true => None,
+            false => Some(Pattern::new(bytes, mask))
+        }
+    }
+
+    // PE images list their functions, otherwise the closest endbr64 or aligned start after padding is taken
+    fn function_start(&self, rva: usize) -> Option<usize> {
+        if self.format == ImageFormat::Pe {
+            if let Ok(Some((start, _))) = pe::function_at(self.data, rva) {
+                return Some(start);
+            }
+        }
+
+        (rva.saturating_sub(MAX_FUNCTION_DISTANCE).max(1)..=rva).rev().find(|&start| {
+            self.data.get(start..start + ENDBR64.len()) == Some(&ENDBR64)
+                || (start % 0x10 == 0 && matches!(self.data.get(start - 1), Some(0xCC | 0x90 | 0xC3)))
+        })
+    }
+
+    // best match of `window` around `predicted`, preferring the closest on ties
+    fn refine(&self, window: &Pattern, predicted: usize) -> Option<(usize, f32)> {
+        let mut best: Option<(usize, f32)> = None;
+
+        // candidates past the end of the image can't hold the window
+        let last = predicted.saturating_add(SEARCH_DISTANCE).min(self.data.len().saturating_sub(window.len()));
+
+        for candidate in predicted.saturating_sub(SEARCH_DISTANCE)..=last {
+            let similarity = match self.data.get(candidate..candidate + window.len()) {
+                Some(bytes) => window.similarity(bytes),
+                None => continue
+            };
+
+            let better = match best {
+                Some((best, best_similarity)) => similarity > best_similarity
+                    || (similarity == best_similarity && candidate.abs_diff(predicted) < best.abs_diff(predicted)),
+                None => true
+            };
+
+            if better {
+                best = Some((candidate, similarity));
+            }
+        }
+
+        best.filter(|(_, similarity)| *similarity >= MIN_SIMILARITY)
+    }
+
+    // rip relative targets -> the instructions referencing them, over all code
+    fn references(&self) -> &HashMap<usize, Vec<usize>> {
+        self.references.get_or_init(|| self.find_references())
+    }
+
+    fn find_references(&self) -> HashMap<usize, Vec<usize>> {
+        let mut references: HashMap<usize, Vec<usize>> = HashMap::new();
+
+        for section in &self.code {
+            let mut decoder = match self.decoder(section.rva, section.rva.saturating_add(section.size)) {
+                Some(decoder) => decoder,
+                None => continue
+            };
+
+            let mut instruction = Instruction::default();
+
+            while decoder.can_decode() {
+                decoder.decode_out(&mut instruction);
+
+                if instruction.is_ip_rel_memory_operand() {
+                    references.entry(instruction.ip_rel_memory_address() as usize)
+                        .or_default()
+                        .push(instruction.ip() as usize);
+                }
+            }
+        }
+
+        references
+    }
+
+    // printable strings referenced by the code in `start..end`, with the referencing instruction
+    fn referenced_strings(&self, start: usize, end: usize) -> Vec<(usize, Vec<u8>)> {
+        let mut decoder = match self.decoder(start, end) {
+            Some(decoder) => decoder,
+            None => return Vec::new()
+        };
+
+        let mut instruction = Instruction::default();
+        let mut strings: Vec<(usize, Vec<u8>)> = Vec::new();
+
+        while decoder.can_decode() {
+            decoder.decode_out(&mut instruction);
+
+            if !instruction.is_ip_rel_memory_operand() {
+                continue;
+            }
+
+            let target = instruction.ip_rel_memory_address() as usize;
+
+            let bytes = match self.data.get(target..) {
+                Some(bytes) => &bytes[..bytes.len().min(MAX_STRING_LENGTH)],
+                None => continue
+            };
+
+            let length = match bytes.iter().position(|byte| *byte == 0) {
+                Some(length) => length,
+                None => continue
+            };
+
+            if length >= MIN_STRING_LENGTH && bytes[..length].iter().all(|byte| byte.is_ascii_graphic() || *byte == b' ') {
+                strings.push((instruction.ip() as usize, bytes[..length].to_vec()));
+            }
+        }
+
+        strings
+    }
+
+    fn rip_target(&self, rva: usize) -> Option<usize> {
+        let mut decoder = self.decoder(rva, rva.saturating_add(0x10))?;
+        let instruction = decoder.decode();
+
+        match instruction.is_ip_rel_memory_operand() {
+            true => Some(instruction.ip_rel_memory_address() as usize),
+            false => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+    use crate::image::{elf::{Elf64_Ehdr, Elf64_Phdr, ELFMAGIC, PF_R, PF_W, PF_X, PT_LOAD}, read, write};
+    use super::*;
+
+    // an image with code mapped executable below 0x800 and data above it, with `bytes` placed at each of its offsets
+    fn image(bytes: &[(usize, &[u8])]) -> Vec<u8> {
+        let mut data = vec![0u8; 0x1000];
+
+        let mut ehdr: Elf64_Ehdr = read(&data, 0).unwrap();
+        ehdr.e_ident[0..4].copy_from_slice(&ELFMAGIC);
+        ehdr.e_phoff = size_of::<Elf64_Ehdr>() as u64;
+        ehdr.e_phentsize = size_of::<Elf64_Phdr>() as u16;
+        ehdr.e_phnum = 2;
+        write(&mut data, 0, ehdr).unwrap();
+
+        let text = Elf64_Phdr { p_type: PT_LOAD, p_flags: PF_R | PF_X, p_offset: 0, p_vaddr: 0, p_paddr: 0, p_filesz: 0x800, p_memsz: 0x800, p_align: 0x1000 };
+        let data_segment = Elf64_Phdr { p_type: PT_LOAD, p_flags: PF_R | PF_W, p_offset: 0x800, p_vaddr: 0x800, p_paddr: 0x800, p_filesz: 0x800, p_memsz: 0x800, p_align: 0x1000 };
+        write(&mut data, 0x40, text).unwrap();
+        write(&mut data, 0x40 + size_of::<Elf64_Phdr>(), data_segment).unwrap();
+
+        for (offset, bytes) in bytes {
+            data[*offset..*offset + bytes.len()].copy_from_slice(bytes);
+        }
+
+        data
+    }
+
+    // add eax, imm8 for each value, three bytes apiece
+    fn adds(values: impl IntoIterator<Item = u8>) -> Vec<u8> {
+        values.into_iter().flat_map(|value| [0x83, 0xC0, value]).collect()
+    }
+
+    // `opcode reg, [rip + target]` at `rva`
+    fn rip(opcode: [u8; 3], rva: usize, target: usize) -> Vec<u8> {
+        let displacement = target as i32 - (rva + 7) as i32;
+        [&opcode[..], &displacement.to_le_bytes()].concat()
+    }
+
+    fn function(body: &[&[u8]]) -> Vec<u8> {
+        [&ENDBR64[..], &body.concat(), &[0xC3]].concat()
+    }
+
+    // the window bytes at each offset with the first `changed` of them overwritten
+    fn image_with_changed(sites: &[(usize, usize)]) -> Vec<u8> {
+        let sites: Vec<(usize, Vec<u8>)> = sites.iter()
+            .map(|(offset, changed)| {
+                let mut bytes = adds(0x40..0x4B);
+                bytes[..*changed].fill(0x90);
+                (*offset, bytes)
+            })
+            .collect();
+
+        let sites: Vec<(usize, &[u8])> = sites.iter().map(|(offset, bytes)| (*offset, bytes.as_slice())).collect();
+
+        image(&sites)
+    }
+
+    #[test]
+    fn function_start_finds_endbr64_and_padding() {
+        let data = image(&[
+            (0x300, &function(&[&adds(1..=16)])),
+            (0x400, &[0xCC; 0x10]),
+            (0x410, &adds(1..=16)),
+            (0x47F, &[0xC3]),
+            (0x480, &adds(1..=16)),
+            (0x4F8, &[0xCC]),
+            (0x4F9, &adds(1..=4))
+        ]);
+        let image = Image::new(&data).unwrap();
+
+        assert_eq!(image.function_start(0x320), Some(0x300));
+        assert_eq!(image.function_start(0x420), Some(0x410));
+        assert_eq!(image.function_start(0x490), Some(0x480));
+
+        // padding only counts in front of an aligned start
+        assert_eq!(image.function_start(0x500), Some(0x480));
+    }
+
+    #[test]
+    fn refine_prefers_the_closest_of_equal_matches() {
+        let window = Pattern::new(adds(0x40..0x4B), vec![0xFF; 33]);
+        let data = image(&[(0x300, &adds(0x40..0x4B)), (0x340, &adds(0x40..0x4B))]);
+        let image = Image::new(&data).unwrap();
+
+        assert_eq!(image.refine(&window, 0x310), Some((0x300, 1.0)));
+        assert_eq!(image.refine(&window, 0x330), Some((0x340, 1.0)));
+
+        // a better match wins over a closer one
+        let data = image_with_changed(&[(0x300, 3), (0x340, 0)]);
+        assert_eq!(Image::new(&data).unwrap().refine(&window, 0x310).map(|(site, _)| site), Some(0x340));
+    }
+
+    #[test]
+    fn refine_needs_the_minimum_similarity() {
+        let window = Pattern::new(adds(0x40..0x4B), vec![0xFF; 33]);
+
+        // 20 of 33 bytes still matching is just above the cutoff, 19 just below
+        for (changed, expected) in [(13, true), (14, false)] {
+            let data = image_with_changed(&[(0x300, changed)]);
+            let refined = Image::new(&data).unwrap().refine(&window, 0x300);
+
+            assert_eq!(refined.is_some(), expected, "{} changed", changed);
+
+            if let Some((site, similarity)) = refined {
+                assert_eq!(site, 0x300);
+                assert!((MIN_SIMILARITY..1.0).contains(&similarity));
+            }
+        }
+    }
+
+    #[test]
+    fn migrate_finds_a_moved_function() {
+        let code = function(&[&adds(1..=32)]);
+
+        let old = image(&[(0x300, &code)]);
+        let new = image(&[(0x180, &[0x90; 4]), (0x500, &code)]);
+
+        let migration = migrate(&old, 0x322, &new).unwrap();
+
+        assert_eq!((migration.old_site, migration.new_site, migration.method), (0x322, 0x522, Method::Function));
+        assert_eq!(migration.similarity, 1.0);
+        assert_eq!(migration.resolved, None);
+        assert_eq!(migration.generated.rva, 0x522);
+    }
+
+    #[test]
+    fn migrate_falls_back_to_the_bytes_around_the_site() {
+        let old = image(&[(0x300, &function(&[&adds(1..=10), &adds(0x40..0x4C)]))]);
+        let new = image(&[(0x600, &function(&[&adds(0x20..0x25), &adds(0x40..0x4C)]))]);
+
+        let migration = migrate(&old, 0x322, &new).unwrap();
+
+        assert_eq!((migration.new_site, migration.method, migration.similarity), (0x613, Method::Bytes, 1.0));
+    }
+
+    #[test]
+    fn migrate_follows_string_references() {
+        let mut changed = adds(0x40..0x4C);
+        changed[2 + 4 * 3] = 0x99;
+
+        let old = image(&[
+            (0x300, &function(&[&rip([0x48, 0x8D, 0x0D], 0x304, 0x900), &adds(0x40..0x4C)])),
+            (0x900, b"migrate me\0")
+        ]);
+
+        // a different prologue, the string moved and one of the values changed
+        let new = image(&[
+            (0x600, &function(&[&[0x53], &rip([0x48, 0x8D, 0x0D], 0x605, 0xA00), &changed])),
+            (0xA00, b"migrate me\0")
+        ]);
+
+        let migration = migrate(&old, 0x30E, &new).unwrap();
+
+        assert_eq!((migration.new_site, migration.method), (0x60F, Method::String));
+        assert_eq!(migration.similarity, 32.0 / 33.0);
+    }
+
+    #[test]
+    fn migrate_resolves_moved_data_addresses() {
+        let old = image(&[(0x300, &function(&[&adds(1..=10), &rip([0x48, 0x8B, 0x05], 0x322, 0x880), &adds(11..=20)]))]);
+        let new = image(&[(0x500, &function(&[&adds(1..=10), &rip([0x48, 0x8B, 0x05], 0x522, 0x8C0), &adds(11..=20)]))]);
+
+        let migration = migrate(&old, 0x880, &new).unwrap();
+
+        assert_eq!((migration.old_site, migration.new_site, migration.method), (0x322, 0x522, Method::Function));
+        assert_eq!(migration.resolved, Some(0x8C0));
+
+        // nothing references this one
+        assert!(matches!(migrate(&old, 0x900, &new), Err(Error::InvalidOperation(_))));
+    }
+}
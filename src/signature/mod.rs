@@ -0,0 +1,242 @@
+pub mod migrate;
+
+use std::fs;
+use iced_x86::{ConstantOffsets, Decoder, DecoderOptions, Formatter, Instruction, IntelFormatter, OpKind};
+use crate::{error::{Error, Result}, game::{modules::find_dump, offsets::{Operation, Signature}}, image, pattern::Pattern};
+
+const MAX_SIGNATURE_LENGTH: usize = 0x80;
+
+// immediates up to this size are kept, larger ones tend to be addresses or tuned constants
+const MAX_KEPT_IMMEDIATE: u64 = 0xFFFF;
+
+pub struct Generated {
+    pub pattern: Pattern,
+    pub rva: usize,
+    pub instructions: Vec<String>,
+    // where the displacement of a rip relative first instruction sits and the instruction length
+    pub rip: Option<(usize, usize)>
+}
+
+// prints the shortest signature that only matches `rva` in the dump of `module`
+pub fn run(module: &str, rva: usize) {
+    let path = match find_dump(module) {
+        Ok(path) => path,
+        Err(err) => {
+            println!("failed to find a dump of module: {}, error: {}", module, err);
+            return;
+        }
+    };
+
+    let generated = match fs::read(&path).map_err(Into::into).and_then(|data| generate(&data, rva)) {
+        Ok(generated) => generated,
+        Err(err) => {
+            println!("failed to generate signature: {} at 0x{:X}, error: {}", path.display(), rva, err);
+            return;
+        }
+    };
+
+    print(module, generated);
+}
+
+// the signature, the instructions it covers and a `signatures` entry for config.json
+fn print(module: &str, generated: Generated) {
+    println!("signature: {}", generated.pattern);
+    println!("matches once at 0x{:X} ({} bytes)", generated.rva, generated.pattern.len());
+
+    for instruction in &generated.instructions {
+        println!("    {}", instruction);
+    }
+
+    let signature = Signature {
+        module: module.to_string(),
+        pattern: generated.pattern,
+        operations: generated.rip
+            .map(|(offset, length)| vec![Operation::Rip { offset, length }])
+            .unwrap_or_default()
+    };
+
+    if let Ok(json) = serde_json::to_string_pretty(&signature) {
+        println!("{}", json);
+    }
+}
+
+// decodes from `rva` and wildcards whatever moves between builds, one instruction at a time until
+// the pattern is unique in the image, then trims it to the shortest prefix that still is
+pub fn generate(data: &[u8], rva: usize) -> Result<Generated> {
+    let section = image::sections(data)?.into_iter()
+        .find(|section| section.executable && (section.rva..section.rva + section.size).contains(&rva))
+        .ok_or_else(|| Error::InvalidOperation(format!("0x{:X} is not inside an executable section", rva)))?;
+
+    let end = (section.rva + section.size).min(data.len());
+    let code = data.get(rva..end).ok_or(Error::InvalidImage)?;
+
+    let mut decoder = Decoder::with_ip(64, code, rva as u64, DecoderOptions::NONE);
+    let mut formatter = IntelFormatter::new();
+    let mut instruction = Instruction::default();
+
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut mask: Vec<u8> = Vec::new();
+    let mut instructions: Vec<String> = Vec::new();
+    let mut rip = None;
+
+    // offsets matching everything decoded so far
+    let mut candidates: Option<Vec<usize>> = None;
+
+    while decoder.can_decode() && bytes.len() < MAX_SIGNATURE_LENGTH {
+        decoder.decode_out(&mut instruction);
+
+        if instruction.is_invalid() {
+            break;
+        }
+
+        let offsets = decoder.get_constant_offsets(&instruction);
+
+        let start = bytes.len();
+        bytes.extend_from_slice(&code[start..start + instruction.len()]);
+        mask.resize(bytes.len(), 0xFF);
+
+        for (offset, size) in relocatable(&instruction, &offsets) {
+            mask[start + offset..start + offset + size].fill(0);
+        }
+
+        if start == 0 && instruction.is_ip_rel_memory_operand() {
+            rip = Some((offsets.displacement_offset(), instruction.len()));
+        }
+
+        let mut text = String::new();
+        formatter.format(&instruction, &mut text);
+        instructions.push(format!("0x{:X}: {}", instruction.ip(), text));
+
+        let pattern = Pattern::new(bytes.clone(), mask.clone());
+
+        // anything matching the longer pattern matched the shorter one before it
+        let previous = candidates.take();
+
+        let scan = |pattern: &Pattern| -> Vec<usize> {
+            match &previous {
+                Some(previous) => previous.iter().copied().filter(|&offset| pattern.matches(&data[offset..])).collect(),
+                None => pattern.scan(data)
+            }
+        };
+
+        let matches = scan(&pattern);
+
+        if matches == [rva] {
+            // the last instruction made it unique, but maybe not all of it is needed
+            let length = (start + 1..=bytes.len())
+                .filter(|length| mask[length - 1] != 0)
+                .find(|&length| scan(&Pattern::new(bytes[..length].to_vec(), mask[..length].to_vec())) == [rva])
+                .unwrap_or(bytes.len());
+
+            return Ok(Generated {
+                pattern: Pattern::new(bytes[..length].to_vec(), mask[..length].to_vec()),
+                rva,
+                instructions,
+                rip
+            });
+        }
+
+        candidates = Some(matches);
+    }
+
+    Err(Error::InvalidOperation(format!("no unique signature within 0x{:X} bytes of 0x{:X}", MAX_SIGNATURE_LENGTH, rva)))
+}
+
+// rip relative and absolute addresses, branch targets and large immediates
+fn relocatable(instruction: &Instruction, offsets: &ConstantOffsets) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    if offsets.has_displacement() && (instruction.is_ip_rel_memory_operand() || offsets.displacement_size() == 8) {
+        ranges.push((offsets.displacement_offset(), offsets.displacement_size()));
+    }
+
+    let branch = (0..instruction.op_count())
+        .any(|i| matches!(instruction.op_kind(i), OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64 | OpKind::FarBranch32));
+
+    let large = (0..instruction.op_count()).any(|i| {
+        let value = match instruction.op_kind(i) {
+            OpKind::Immediate32 => instruction.immediate32() as i32 as i64,
+            OpKind::Immediate32to64 | OpKind::Immediate64 => instruction.immediate(i) as i64,
+            _ => return false
+        };
+
+        value.unsigned_abs() > MAX_KEPT_IMMEDIATE
+    });
+
+    if offsets.has_immediate() && (branch || large) {
+        ranges.push((offsets.immediate_offset(), offsets.immediate_size()));
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+    use crate::image::{elf::{Elf64_Ehdr, Elf64_Phdr, ELFMAGIC, PF_R, PF_X, PT_LOAD}, read, write};
+    use super::*;
+
+    // an image mapped as one executable segment, with `code` placed at each of its offsets
+    fn image(code: &[(usize, &[u8])]) -> Vec<u8> {
+        let mut data = vec![0u8; 0x1000];
+
+        let mut ehdr: Elf64_Ehdr = read(&data, 0).unwrap();
+        ehdr.e_ident[0..4].copy_from_slice(&ELFMAGIC);
+        ehdr.e_phoff = size_of::<Elf64_Ehdr>() as u64;
+        ehdr.e_phentsize = size_of::<Elf64_Phdr>() as u16;
+        ehdr.e_phnum = 1;
+        write(&mut data, 0, ehdr).unwrap();
+
+        let load = Elf64_Phdr { p_type: PT_LOAD, p_flags: PF_R | PF_X, p_offset: 0, p_vaddr: 0, p_paddr: 0, p_filesz: 0x1000, p_memsz: 0x1000, p_align: 0x1000 };
+        write(&mut data, 0x40, load).unwrap();
+
+        for (offset, bytes) in code {
+            data[*offset..*offset + bytes.len()].copy_from_slice(bytes);
+        }
+
+        data
+    }
+
+    fn relocatable_ranges(code: &[u8]) -> Vec<(usize, usize)> {
+        let mut decoder = Decoder::with_ip(64, code, 0, DecoderOptions::NONE);
+        let instruction = decoder.decode();
+
+        relocatable(&instruction, &decoder.get_constant_offsets(&instruction))
+    }
+
+    #[test]
+    fn generate_extends_until_unique_and_trims() {
+        // mov rax, [rip+disp32] followed by test rax, rax or mov rcx, rax
+        let data = image(&[
+            (0x100, &[0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00, 0x48, 0x85, 0xC0, 0xC3]),
+            (0x200, &[0x48, 0x8B, 0x05, 0x20, 0x00, 0x00, 0x00, 0x48, 0x89, 0xC1, 0xC3])
+        ]);
+
+        let generated = generate(&data, 0x100).unwrap();
+
+        assert_eq!(generated.pattern.to_string(), "48 8B 05 ? ? ? ? 48 85");
+        assert_eq!(generated.rva, 0x100);
+        assert_eq!(generated.instructions.len(), 2);
+        assert_eq!(generated.rip, Some((3, 7)));
+    }
+
+    #[test]
+    fn generate_rejects_addresses_outside_code() {
+        let data = image(&[]);
+
+        assert!(matches!(generate(&data, 0x2000), Err(Error::InvalidOperation(_))));
+    }
+
+    #[test]
+    fn relocatable_masks_addresses_and_large_immediates() {
+        // call rel32, mov rax, [rip+disp32], mov eax, imm32 and movabs rax, imm64
+        assert_eq!(relocatable_ranges(&[0xE8, 0x10, 0x00, 0x00, 0x00]), [(1, 4)]);
+        assert_eq!(relocatable_ranges(&[0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00]), [(3, 4)]);
+        assert_eq!(relocatable_ranges(&[0xB8, 0x78, 0x56, 0x34, 0x12]), [(1, 4)]);
+        assert_eq!(relocatable_ranges(&[0x48, 0xB8, 0xF0, 0xDE, 0xBC, 0x9A, 0x78, 0x56, 0x34, 0x12]), [(2, 8)]);
+
+        // small immediates and register operands stay
+        assert_eq!(relocatable_ranges(&[0xB8, 0x05, 0x00, 0x00, 0x00]), []);
+        assert_eq!(relocatable_ranges(&[0x48, 0x85, 0xC0]), []);
+    }
+}